@@ -1,61 +1,518 @@
-use anyhow::Result;
-use reqwest::Client;
-use serde_json::Value;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, Utc};
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use futures::future::BoxFuture;
 use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio::time;
-
-use crate::config::ApiConfig;
-use crate::event::Event;
-use crate::json;
-
-pub fn start(api_configs: Vec<ApiConfig>, tx: mpsc::UnboundedSender<Event>) {
-    let client = Client::new();
-
-    for config in api_configs {
-        if !config.enabled {
-            continue;
-        }
-        
-        let tx = tx.clone();
-        let client = client.clone();
-        
-        tokio::spawn(async move {
-            let mut interval = time::interval(Duration::from_secs(config.interval_sec));
-            // First tick finishes immediately
-            interval.tick().await;
-
-            loop {
-                interval.tick().await;
-                
-                match fetch_and_process(&client, &config).await {
-                    Ok(Some(msg)) => {
-                        let _ = tx.send(Event::Message(format!("[{}] {}", config.name, msg)));
+use tokio::time::{self, Instant};
+use tracing::{debug, error};
+
+use crate::backoff::Backoff;
+use crate::clock::Clock;
+use crate::config::{ApiConfig, Auth, RequestBody};
+use crate::items::ItemTracker;
+use crate::source::{PayloadSpec, Source, SourceContext};
+
+/// Upper bound for delays requested by servers.
+const MAX_SERVER_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Validators and server-requested delays remembered between polls.
+#[derive(Debug, Default)]
+struct PollState {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Delay requested by `Retry-After` or rate-limit headers.
+    retry_after: Option<Duration>,
+}
+
+/// A successful response.
+struct Fetched {
+    body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Polls an HTTP API on a fixed interval or cron schedule.
+pub struct ApiSource {
+    config: ApiConfig,
+    client: Client,
+}
+
+impl ApiSource {
+    pub fn new(config: ApiConfig, client: Client) -> Self {
+        Self { config, client }
+    }
+
+    async fn poll(self, ctx: &SourceContext) {
+        let config = &self.config;
+        let spec = PayloadSpec::from(config);
+        // The first response only marks the current items as seen
+        let mut tracker = ItemTracker::new(true);
+        let mut state = PollState::default();
+        let mut schedule = Schedule::new(config);
+
+        loop {
+            if ctx.is_shutting_down() {
+                return;
+            }
+            if !ctx.is_enabled() {
+                tokio::select! {
+                    _ = ctx.wait_until_enabled() => {}
+                    _ = ctx.shutdown() => return,
+                }
+                // Fetch right away when the source is enabled again
+                schedule.fetch_now();
+            }
+
+            let Some(at) = schedule.due(ctx.clock()) else {
+                error!(source = %config.name, "cron expression never matches; polling stopped");
+                return;
+            };
+            tokio::select! {
+                _ = time::sleep_until(at) => {}
+                _ = ctx.stopped() => {
+                    if ctx.is_shutting_down() {
+                        return;
                     }
-                    Ok(None) => {
-                        // Data not found or empty, ignore
+                    // Disabled: wait until enabled again
+                    continue;
+                }
+            }
+            schedule.started();
+
+            if !is_active(config, &ctx.clock().now()) {
+                continue;
+            }
+
+            let result = fetch(&self.client, config, &mut state, ctx.clock())
+                .await
+                .and_then(|fetched| match fetched {
+                    Some(fetched) => {
+                        let messages = spec.messages(&fetched.body, &mut tracker)?;
+                        // Only a payload that was shown may be skipped as not modified
+                        state.etag = fetched.etag;
+                        state.last_modified = fetched.last_modified;
+                        Ok(messages)
                     }
-                    Err(e) => {
-                        eprintln!("API Error [{}]: {:?}", config.name, e);
+                    None => Ok(vec![]),
+                });
+            let retry_after = state.retry_after.take();
+            match result {
+                Ok(messages) => {
+                    ctx.success();
+                    schedule.succeeded(retry_after, ctx.clock());
+                    for msg in messages {
+                        ctx.emit(msg);
                     }
                 }
+                Err(e) => {
+                    ctx.failure(&e);
+                    schedule.failed(retry_after);
+                }
             }
+        }
+    }
+}
+
+/// When the next request of a source is due.
+///
+/// After a failure the backoff delay replaces the normal schedule; the
+/// server may ask for a longer wait either way. Jitter only delays requests
+/// made on the normal schedule: the first request, one made on enabling
+/// and retries (whose backoff is randomized already) are not delayed.
+#[derive(Debug)]
+struct Schedule<'a> {
+    config: &'a ApiConfig,
+    backoff: Backoff,
+    /// Overrides the normal schedule for the next request.
+    next: Option<Instant>,
+    /// Random delay added to the next request on the normal schedule.
+    jitter: Duration,
+}
+
+impl<'a> Schedule<'a> {
+    /// Fixed intervals fetch immediately; cron schedules wait for their
+    /// first match.
+    fn new(config: &'a ApiConfig) -> Self {
+        Self {
+            config,
+            backoff: Backoff::new(config.backoff.clone()),
+            next: match &config.cron {
+                Some(_) => None,
+                None => Some(Instant::now()),
+            },
+            jitter: jitter(config.jitter_sec),
+        }
+    }
+
+    /// Returns when the next request is due, or `None` if the cron
+    /// expression never matches.
+    fn due(&self, clock: &Clock) -> Option<Instant> {
+        self.next.or_else(|| next_run(self.config, clock).map(|at| at + self.jitter))
+    }
+
+    fn fetch_now(&mut self) {
+        self.next = Some(Instant::now());
+    }
+
+    /// Called when the due request is made.
+    fn started(&mut self) {
+        self.next = None;
+    }
+
+    fn succeeded(&mut self, retry_after: Option<Duration>, clock: &Clock) {
+        self.backoff.reset();
+        self.jitter = jitter(self.config.jitter_sec);
+        self.next = retry_after.map(|delay| {
+            let at = Instant::now() + delay;
+            next_run(self.config, clock).map_or(at, |scheduled| scheduled.max(at))
         });
     }
+
+    fn failed(&mut self, retry_after: Option<Duration>) {
+        let delay = self.backoff.next_delay().max(retry_after.unwrap_or_default());
+        self.next = Some(Instant::now() + delay);
+    }
 }
 
-async fn fetch_and_process(client: &Client, config: &ApiConfig) -> Result<Option<String>> {
-    let resp = client.get(&config.url).send().await?;
-    
-    if !resp.status().is_success() {
+impl Source for ApiSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "api"
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn run(self: Box<Self>, ctx: SourceContext) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            // Dropped on shutdown, which also aborts a request in flight
+            tokio::select! {
+                _ = ctx.shutdown() => {}
+                _ = self.poll(&ctx) => {}
+            }
+        })
+    }
+}
+
+/// Returns when the next request is due according to `cron` or `interval_sec`.
+fn next_run(config: &ApiConfig, clock: &Clock) -> Option<Instant> {
+    match &config.cron {
+        Some(cron) => {
+            let now = clock.now();
+            let wait = (cron.next_after(&now)? - now).to_std().unwrap_or_default();
+            Some(Instant::now() + wait)
+        }
+        None => Some(Instant::now() + Duration::from_secs(config.interval_sec)),
+    }
+}
+
+/// Returns a random delay of up to `max_sec` seconds.
+fn jitter(max_sec: u64) -> Duration {
+    if max_sec == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(fastrand::u64(0..=max_sec * 1000))
+}
+
+fn is_active(config: &ApiConfig, now: &DateTime<Local>) -> bool {
+    config.active_hours.is_empty() || config.active_hours.iter().any(|w| w.contains(now))
+}
+
+/// Sends a request and returns the body and its validators, or `None` if it
+/// was not modified since the last poll.
+async fn fetch(
+    client: &Client,
+    config: &ApiConfig,
+    state: &mut PollState,
+    clock: &Clock,
+) -> Result<Option<Fetched>> {
+    let mut request = build_request(client, config)?;
+    if let Some(etag) = &state.etag {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &state.last_modified {
+        request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let resp = request.send().await.map_err(reqwest::Error::without_url)?;
+    state.retry_after = server_delay(resp.status(), resp.headers(), clock.now().with_timezone(&Utc));
+
+    debug!(source = %config.name, status = %resp.status(), "response received");
+
+    if resp.status() == StatusCode::NOT_MODIFIED {
         return Ok(None);
     }
+    if !resp.status().is_success() {
+        bail!("HTTP {}", resp.status());
+    }
+
+    let etag = header_string(resp.headers(), header::ETAG);
+    let last_modified = header_string(resp.headers(), header::LAST_MODIFIED);
+    let body = read_body(resp, config.max_body_bytes).await?;
+    Ok(Some(Fetched {
+        body,
+        etag,
+        last_modified,
+    }))
+}
+
+/// Reads the (decoded) body, failing if it is larger than `limit` bytes.
+async fn read_body(mut resp: Response, limit: usize) -> Result<Vec<u8>> {
+    if let Some(len) = resp.content_length()
+        && len > limit as u64
+    {
+        bail!("response body of {} bytes exceeds the limit of {} bytes", len, limit);
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(reqwest::Error::without_url)? {
+        if body.len() + chunk.len() > limit {
+            bail!("response body exceeds the limit of {} bytes", limit);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
+
+/// Returns how long the server asked us to wait before the next request,
+/// from `Retry-After` or exhausted rate-limit headers.
+fn server_delay(status: StatusCode, headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let retry_after = if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        header_string(headers, header::RETRY_AFTER).and_then(|value| {
+            match value.trim().parse::<u64>() {
+                Ok(secs) => Some(Duration::from_secs(secs)),
+                Err(_) => {
+                    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+                    (date.with_timezone(&Utc) - now).to_std().ok()
+                }
+            }
+        })
+    } else {
+        None
+    };
 
-    if let Some(keys) = &config.json_keys {
-        let json: Value = resp.json().await?;
-        Ok(json::extract_message(&json, keys))
+    let rate_limited = ["x-ratelimit-remaining", "ratelimit-remaining"]
+        .iter()
+        .filter_map(|name| headers.get(*name)?.to_str().ok()?.trim().parse::<u64>().ok())
+        .any(|remaining| remaining == 0);
+    let rate_limit_reset = if rate_limited {
+        ["x-ratelimit-reset", "ratelimit-reset"]
+            .iter()
+            .filter_map(|name| headers.get(*name)?.to_str().ok()?.trim().parse::<i64>().ok())
+            .next()
+            .and_then(|reset| {
+                // Large values are Unix timestamps, small ones are seconds from now
+                let secs = if reset > 1_000_000_000 {
+                    reset - now.timestamp()
+                } else {
+                    reset
+                };
+                u64::try_from(secs).ok().map(Duration::from_secs)
+            })
     } else {
-        let text = resp.text().await?;
-        Ok(Some(text))
+        None
+    };
+
+    retry_after
+        .into_iter()
+        .chain(rate_limit_reset)
+        .max()
+        .map(|delay| delay.min(MAX_SERVER_DELAY))
+}
+
+fn build_request(client: &Client, config: &ApiConfig) -> Result<RequestBuilder> {
+    let method = match &config.method {
+        Some(method) => Method::from_bytes(method.to_uppercase().as_bytes())
+            .with_context(|| format!("invalid HTTP method {}", method))?,
+        None => Method::GET,
+    };
+
+    let mut request = client
+        .request(method, &config.url)
+        .timeout(Duration::from_secs(config.timeout_sec));
+
+    if !config.query.is_empty() {
+        request = request.query(&config.query);
+    }
+    for (name, value) in &config.headers {
+        request = request.header(name, value.resolve()?);
+    }
+
+    request = match &config.auth {
+        Some(Auth::Bearer { token }) => request.bearer_auth(token.resolve()?),
+        Some(Auth::Basic { username, password }) => {
+            let password = password.as_ref().map(|p| p.resolve()).transpose()?;
+            request.basic_auth(username, password)
+        }
+        None => request,
+    };
+
+    request = match &config.body {
+        Some(RequestBody::Json(json)) => request.json(json),
+        Some(RequestBody::Form(form)) => request.form(form),
+        Some(RequestBody::Text(text)) => request.body(text.clone()),
+        None => request,
+    };
+
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn config(toml: &str) -> ApiConfig {
+        toml::from_str(&format!("name = \"svc\"\nurl = \"http://localhost\"\n{}", toml)).unwrap()
+    }
+
+    fn clock() -> Clock {
+        Clock::tokio(Local.with_ymd_and_hms(2025, 1, 6, 12, 0, 30).unwrap())
+    }
+
+    fn secs(start: Instant, at: Option<Instant>) -> u64 {
+        (at.unwrap() - start).as_secs()
+    }
+
+    /// Marks the due request as made and advances to it.
+    async fn run_due(schedule: &mut Schedule<'_>, clock: &Clock) {
+        time::sleep_until(schedule.due(clock).unwrap()).await;
+        schedule.started();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn intervals_fetch_at_once_and_then_after_each_request() {
+        let config = config("interval_sec = 60");
+        let clock = clock();
+        let start = Instant::now();
+        let mut schedule = Schedule::new(&config);
+        assert_eq!(secs(start, schedule.due(&clock)), 0);
+
+        // The interval counts from the end of each request
+        for _ in 0..3 {
+            run_due(&mut schedule, &clock).await;
+            time::advance(Duration::from_secs(2)).await;
+            let done = Instant::now();
+            schedule.succeeded(None, &clock);
+            assert_eq!(secs(done, schedule.due(&clock)), 60);
+        }
+        assert_eq!(secs(start, schedule.due(&clock)), 186);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cron_schedules_follow_the_clock() {
+        let config = config("cron = \"*/5 * * * *\"");
+        let clock = clock();
+        let start = Instant::now();
+        let mut schedule = Schedule::new(&config);
+
+        // 12:05:00, 12:10:00 and 12:15:00
+        for expected in [270, 570, 870] {
+            assert_eq!(secs(start, schedule.due(&clock)), expected);
+            run_due(&mut schedule, &clock).await;
+            schedule.succeeded(None, &clock);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn never_matching_cron_schedules_are_never_due() {
+        let config = config("cron = \"0 0 31 2 *\"");
+        assert_eq!(Schedule::new(&config).due(&clock()), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backoff_grows_until_a_success() {
+        let config = config("interval_sec = 3600\nbackoff = { initial_sec = 10, max_sec = 30, multiplier = 2.0 }");
+        let clock = clock();
+        let mut schedule = Schedule::new(&config);
+
+        // Each delay is between half and all of 10s, 20s, 30s (the maximum), 30s
+        for max in [10, 20, 30, 30] {
+            run_due(&mut schedule, &clock).await;
+            let now = Instant::now();
+            schedule.failed(None);
+            let delay = secs(now, schedule.due(&clock));
+            assert!((max / 2..=max).contains(&delay), "{} not within {}", delay, max);
+        }
+
+        // The interval resumes and the backoff starts over
+        run_due(&mut schedule, &clock).await;
+        let now = Instant::now();
+        schedule.succeeded(None, &clock);
+        assert_eq!(secs(now, schedule.due(&clock)), 3600);
+
+        run_due(&mut schedule, &clock).await;
+        let now = Instant::now();
+        schedule.failed(None);
+        assert!((5..=10).contains(&secs(now, schedule.due(&clock))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn servers_may_ask_for_a_longer_wait() {
+        let config = config("interval_sec = 60\nbackoff = { initial_sec = 10, max_sec = 10 }");
+        let clock = clock();
+        let mut schedule = Schedule::new(&config);
+        let now = Instant::now();
+
+        // A short Retry-After does not shorten the interval or the backoff
+        schedule.started();
+        schedule.succeeded(Some(Duration::from_secs(5)), &clock);
+        assert_eq!(secs(now, schedule.due(&clock)), 60);
+        schedule.started();
+        schedule.failed(Some(Duration::from_secs(1)));
+        assert!((5..=10).contains(&secs(now, schedule.due(&clock))));
+
+        schedule.started();
+        schedule.succeeded(Some(Duration::from_secs(600)), &clock);
+        assert_eq!(secs(now, schedule.due(&clock)), 600);
+        schedule.started();
+        schedule.failed(Some(Duration::from_secs(600)));
+        assert_eq!(secs(now, schedule.due(&clock)), 600);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn jitter_only_delays_scheduled_requests() {
+        let config = config("interval_sec = 60\njitter_sec = 30\nbackoff = { initial_sec = 10, max_sec = 10 }");
+        let clock = clock();
+        let mut schedule = Schedule::new(&config);
+        let start = Instant::now();
+        assert_eq!(secs(start, schedule.due(&clock)), 0);
+
+        for _ in 0..10 {
+            schedule.started();
+            schedule.succeeded(None, &clock);
+            assert!((60..=90).contains(&secs(start, schedule.due(&clock))));
+            // Asking again does not draw another delay
+            assert_eq!(schedule.due(&clock), schedule.due(&clock));
+
+            schedule.started();
+            schedule.failed(None);
+            assert!((5..=10).contains(&secs(start, schedule.due(&clock))));
+
+            schedule.fetch_now();
+            assert_eq!(secs(start, schedule.due(&clock)), 0);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn enabling_fetches_at_once() {
+        let config = config("cron = \"0 * * * *\"");
+        let clock = clock();
+        let mut schedule = Schedule::new(&config);
+        let start = Instant::now();
+        assert_eq!(secs(start, schedule.due(&clock)), 3570);
+
+        time::advance(Duration::from_secs(100)).await;
+        schedule.fetch_now();
+        assert_eq!(secs(start, schedule.due(&clock)), 100);
     }
 }
@@ -1,37 +1,127 @@
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent};
-use std::{fs, path::PathBuf};
-use unicode_width::UnicodeWidthStr;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs;
+use tracing::{info, warn};
 
-use crate::config::Config;
-use crate::event::{Event, EventHandler};
-use crate::tui::Tui;
+use crate::clock::Clock;
+use crate::config::{self, Config};
+use crate::event::{Event, EventHandler, Message, MessageStyle, Route, Signal};
+use crate::health::{HealthRegistry, SourceState};
+use crate::history::{History, HistoryKind};
+use crate::limiter::{RateLimiter, Verdict};
+use crate::logging::LogBuffer;
+use crate::rules::RuleSet;
+use crate::ticker::TickerState;
+use crate::tui::{self, Tui};
 use crate::ui;
 
+/// Maximum number of routed messages kept in the ticker rotation.
+const TICKER_ITEMS_LIMIT: usize = 20;
+
+/// Number of held-back messages whose text is shown when DND ends; the
+/// rest are only counted (all of them are in the history).
+const DND_SUMMARY_TEXTS: usize = 3;
+
+/// Messages held back while DND is active: counts per source and the texts
+/// of the latest ones.
+#[derive(Debug, Default)]
+struct DndSummary {
+    /// Messages per source, in order of first appearance.
+    counts: Vec<(String, usize)>,
+    /// Texts of the latest `DND_SUMMARY_TEXTS` messages, oldest first.
+    latest: VecDeque<String>,
+    total: usize,
+}
+
+impl DndSummary {
+    fn push(&mut self, msg: &Message) {
+        match self.counts.iter_mut().find(|(source, _)| *source == msg.source) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((msg.source.clone(), 1)),
+        }
+        if self.latest.len() >= DND_SUMMARY_TEXTS {
+            self.latest.pop_front();
+        }
+        self.latest.push_back(msg.text.clone());
+        self.total += 1;
+    }
+
+    /// The interrupt shown when DND ends.
+    fn text(&self) -> String {
+        let counts = self
+            .counts
+            .iter()
+            .map(|(source, count)| format!("{} {}", source, count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let mut texts = self.latest.iter().map(String::as_str).collect::<Vec<&str>>().join("    ");
+        if self.total > self.latest.len() {
+            texts = format!("latest {}: {}", self.latest.len(), texts);
+        }
+        format!("DND ended: {} message(s) received ({})    {}", self.total, counts, texts)
+    }
+}
+
 /// Application state
 pub struct App {
     pub running: bool,
+    /// Set by Ctrl-Z; the terminal is released before the next draw.
+    suspend_requested: bool,
     pub config: Config,
+    /// The ticker rotation: file content followed by routed messages.
     pub text: String,
-    pub scroll_offset: usize,
+    /// What the ticker shows: the rotation or the history, and interrupts.
+    pub ticker: TickerState,
+    /// Content loaded from the source files.
+    base_text: String,
+    /// Messages routed into the ticker rotation, oldest first.
+    ticker_items: VecDeque<String>,
     
     // --- Interrupt State ---
-    pub interrupt_text: Option<String>,
-    pub interrupt_remaining_ms: usize,
+    /// Colors requested by the message shown as an interrupt.
+    pub interrupt_style: Option<MessageStyle>,
     pub paused_before_interrupt: bool,
-    pub saved_scroll_offset: usize,
 
     // --- User State ---
     pub paused: bool,
     pub dimmed: bool,
 
+    // --- Do-Not-Disturb State ---
+    pub dnd_active: bool,
+    /// Manual toggle; overrides the schedule until the schedule changes.
+    dnd_override: Option<bool>,
+    dnd_scheduled: bool,
+    /// Messages held back while DND is active.
+    dnd_held: DndSummary,
+
+    // --- Message Pipeline ---
+    pub history: History,
+    pub show_history: bool,
+    rules: RuleSet,
+    limiter: RateLimiter,
+    /// Connection state of the network sources.
+    pub health: HealthRegistry,
+    pub show_status: bool,
+    /// Row selected in the status panel.
+    pub status_selected: usize,
+    /// Recent warnings, shown in the log pane.
+    pub logs: LogBuffer,
+    pub show_logs: bool,
+
     // --- Layout State ---
     pub width: usize,
+
+    /// Time for the DND schedule, rate limits and the history.
+    clock: Clock,
 }
 
 impl App {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config, health: HealthRegistry, logs: LogBuffer) -> Self {
         let text = Self::load_content(&config);
+        let rules = RuleSet::new(config.rules.clone());
+        let limiter = RateLimiter::new(&config);
         
         let width = if let Ok((w, _)) = crossterm::terminal::size() {
             w as usize
@@ -39,26 +129,61 @@ impl App {
             80
         };
 
-        Self {
+        let mut app = Self {
             running: true,
+            suspend_requested: false,
             config,
+            base_text: text.clone(),
+            ticker: TickerState::new(text.clone()),
             text,
-            scroll_offset: 0,
-            interrupt_text: None,
-            interrupt_remaining_ms: 0,
+            ticker_items: VecDeque::new(),
+            interrupt_style: None,
             paused_before_interrupt: false,
-            saved_scroll_offset: 0,
             paused: false,
             dimmed: false,
+            dnd_active: false,
+            dnd_override: None,
+            dnd_scheduled: false,
+            dnd_held: DndSummary::default(),
+            history: History::default(),
+            show_history: false,
+            rules,
+            limiter,
+            health,
+            show_status: false,
+            status_selected: 0,
+            logs,
+            show_logs: false,
             width,
-        }
+            clock: Clock::system(),
+        };
+        app.update_styles();
+        app
+    }
+
+    /// Uses `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock the app reads the time from.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Replaces the content loaded from `config.source_files` with `text`.
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.base_text = text.into();
+        self.rebuild_text();
+        self
     }
 
     fn load_content(config: &Config) -> String {
         let mut all_files_content = Vec::new();
 
         for path_str in &config.source_files {
-            let path = Self::expand_path(path_str);
+            let path = config::expand_path(path_str);
             if let Ok(content) = fs::read_to_string(&path) {
                 let file_text = content
                     .lines()
@@ -71,7 +196,7 @@ impl App {
                     all_files_content.push(file_text);
                 }
             } else {
-                eprintln!("Failed to read file: {:?}", path);
+                warn!(path = ?path, "failed to read source file");
             }
         }
         
@@ -82,48 +207,222 @@ impl App {
         }
     }
 
-    fn expand_path(path_str: &str) -> PathBuf {
-        if path_str.starts_with("~") {
-            if let Some(home) = dirs::home_dir() {
-                if path_str == "~" {
-                     home
-                } else if path_str.starts_with("~/") {
-                     home.join(&path_str[2..])
-                } else {
-                     PathBuf::from(path_str)
-                }
-            } else {
-                PathBuf::from(path_str)
-            }
-        } else {
-            PathBuf::from(path_str)
-        }
-    }
-
     pub async fn run(&mut self, terminal: &mut Tui, events: &mut EventHandler) -> Result<()> {
+        let mut tick_rate = self.config.scroll_speed_ms;
         while self.running {
             terminal.draw(|f| ui::draw(f, self))?;
 
             match events.next().await {
-                Some(Event::Tick) => {
-                    if !self.paused {
-                        self.on_tick();
-                    }
-                }
-                Some(Event::Key(key)) => self.handle_key(key, events),
-                Some(Event::Message(msg)) => self.on_message(msg),
-                Some(Event::Resize(w)) => {
-                    self.width = w as usize;
-                }
+                Some(event) => self.handle_event(event),
                 None => break,
             }
+
+            // Speed keys and config reloads change the animation speed
+            if self.config.scroll_speed_ms != tick_rate {
+                tick_rate = self.config.scroll_speed_ms;
+                events.set_tick_rate(tick_rate);
+            }
+
+            if std::mem::take(&mut self.suspend_requested) {
+                tui::suspend(terminal)?;
+            }
         }
         Ok(())
     }
 
-    fn on_message(&mut self, msg: String) {
-        // Play sound
-        let sound_name = &self.config.alert_sound;
+    /// Applies one event. `run` calls this in a loop; programs with their own
+    /// event loop can call it directly and draw with `ui::render`. Ticks
+    /// should come every `config.scroll_speed_ms`.
+    pub fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Tick => {
+                self.update_dnd();
+                self.flush_limiter();
+                if !self.paused {
+                    self.on_tick();
+                }
+            }
+            Event::Key(key) => self.handle_key(key),
+            Event::Signal(signal) => self.on_signal(signal),
+            Event::Message(msg) => self.on_message(msg),
+            Event::Resize(w) => {
+                self.width = w as usize;
+            }
+        }
+    }
+
+    fn on_signal(&mut self, signal: Signal) {
+        info!(?signal, "signal received");
+        match signal {
+            Signal::Hangup if self.config.reload_on_sighup => self.reload_config(),
+            Signal::Terminate | Signal::Interrupt | Signal::Hangup => self.running = false,
+        }
+    }
+
+    /// Re-reads the config file and applies display settings, rules and DND.
+    /// Sources keep running with the settings they were started with.
+    fn reload_config(&mut self) {
+        let Some(path) = config::config_path().filter(|path| path.exists()) else {
+            return;
+        };
+        let mut new_config = match Config::load(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!(error = format!("{:#}", e), "failed to reload config; keeping the current settings");
+                return;
+            }
+        };
+
+        new_config.listen_port = self.config.listen_port;
+        new_config.listener = std::mem::take(&mut self.config.listener);
+        new_config.api_sources = std::mem::take(&mut self.config.api_sources);
+        new_config.ws_sources = std::mem::take(&mut self.config.ws_sources);
+
+        self.rules = RuleSet::new(new_config.rules.clone());
+        self.limiter = RateLimiter::new(&new_config);
+        self.base_text = Self::load_content(&new_config);
+        self.config = new_config;
+        self.rebuild_text();
+        self.update_styles();
+        self.update_dnd();
+        info!(path = ?path, "config reloaded");
+    }
+
+    /// Number of sources whose recent attempts have all failed.
+    pub fn failing_sources(&self) -> usize {
+        self.health
+            .snapshot()
+            .iter()
+            .filter(|(_, health)| health.state == SourceState::Failing)
+            .count()
+    }
+
+    /// Text shown by the ticker when no interrupt is active.
+    pub fn ticker_text(&self) -> Cow<'_, str> {
+        if self.show_history {
+            Cow::Owned(self.history.to_ticker_text())
+        } else {
+            Cow::Borrowed(&self.text)
+        }
+    }
+
+    /// Message pipeline: rules, then rate limits, then delivery.
+    fn on_message(&mut self, msg: Message) {
+        self.health.message(&msg.source, &msg.text);
+
+        let Some(msg) = self.rules.apply(msg) else {
+            return;
+        };
+
+        match self.limiter.check(&msg, self.clock.instant()) {
+            Verdict::Pass => self.deliver(msg),
+            Verdict::Duplicate | Verdict::RateLimited | Verdict::Coalesced => {}
+        }
+    }
+
+    fn deliver(&mut self, msg: Message) {
+        if msg.route == Route::Ticker {
+            self.history.push(self.clock.now(), &msg.source, &msg.text, HistoryKind::Ticker);
+            if msg.sound.is_some() && !self.dnd_active {
+                self.play_sound(msg.sound.as_deref());
+            }
+            self.push_ticker_item(msg.text);
+            return;
+        }
+
+        if self.dnd_active && msg.priority < self.config.dnd.min_priority {
+            self.history.push(self.clock.now(), &msg.source, &msg.text, HistoryKind::Queued);
+            self.dnd_held.push(&msg);
+            self.sync_ticker_content();
+            return;
+        }
+
+        self.history.push(self.clock.now(), &msg.source, &msg.text, HistoryKind::Shown);
+        if !self.dnd_active {
+            self.play_sound(msg.sound.as_deref());
+        }
+
+        self.show_interrupt(msg.text);
+        self.interrupt_style = msg.style;
+        self.update_styles();
+        self.sync_ticker_content();
+    }
+
+    fn push_ticker_item(&mut self, text: String) {
+        if self.ticker_items.len() >= TICKER_ITEMS_LIMIT {
+            self.ticker_items.pop_front();
+        }
+        self.ticker_items.push_back(text);
+        self.rebuild_text();
+    }
+
+    /// Joins the file content and the ticker items into the ticker text.
+    fn rebuild_text(&mut self) {
+        self.text = std::iter::once(self.base_text.as_str())
+            .chain(self.ticker_items.iter().map(String::as_str))
+            .collect::<Vec<&str>>()
+            .join("    ***    ");
+        self.sync_ticker_content();
+    }
+
+    /// Updates the ticker after the rotation or the history changed.
+    fn sync_ticker_content(&mut self) {
+        self.ticker.content = self.ticker_text().into_owned();
+    }
+
+    /// Updates the ticker colors after the config, the dim setting or the
+    /// interrupt changed.
+    fn update_styles(&mut self) {
+        self.ticker.style = ui::base_style(&self.config, self.dimmed);
+        self.ticker.alert_style = ui::alert_style(&self.config, self.interrupt_style.as_ref());
+    }
+
+    /// Records closed rate-limit windows and emits coalesced summaries.
+    fn flush_limiter(&mut self) {
+        let reports = self.limiter.flush(self.clock.instant());
+        if reports.is_empty() {
+            return;
+        }
+        for report in reports {
+            self.history.push(
+                self.clock.now(),
+                &report.source,
+                "",
+                HistoryKind::Suppressed {
+                    duplicates: report.duplicates,
+                    rate_limited: report.rate_limited,
+                    coalesced: report.coalesced,
+                },
+            );
+
+            if report.coalesced > 0 {
+                let text = format!("[{}] {} updates", report.source, report.coalesced);
+                self.deliver(Message::new(report.source, text, report.priority));
+            }
+        }
+        self.sync_ticker_content();
+    }
+
+    fn show_interrupt(&mut self, text: String) {
+        // Keep the pause state from before the first interrupt
+        if self.ticker.interrupt.is_none() {
+            self.paused_before_interrupt = self.paused;
+        }
+        self.paused = false;
+        self.ticker
+            .interrupt(text, (self.config.interrupt_duration_sec * 1000) as usize);
+        self.interrupt_style = None;
+        self.update_styles();
+    }
+
+    fn dismiss_interrupt(&mut self) {
+        self.ticker.dismiss();
+        self.paused = self.paused_before_interrupt;
+    }
+
+    /// Plays the given sound, or the configured alert sound.
+    fn play_sound(&self, sound: Option<&str>) {
+        let sound_name = sound.unwrap_or(&self.config.alert_sound);
         if !sound_name.is_empty() && !sound_name.eq_ignore_ascii_case("None") {
             let mut sound_path = format!("/System/Library/Sounds/{}", sound_name);
             if !sound_name.ends_with(".aiff") {
@@ -140,13 +439,31 @@ impl App {
                 });
             }
         }
+    }
 
-        self.paused_before_interrupt = self.paused;
-        self.saved_scroll_offset = self.scroll_offset;
-        self.paused = false;
-        self.interrupt_text = Some(msg);
-        self.interrupt_remaining_ms = (self.config.interrupt_duration_sec * 1000) as usize;
-        self.scroll_offset = 0;
+    /// Re-evaluates the DND schedule and shows a summary when DND ends.
+    fn update_dnd(&mut self) {
+        let now = self.clock.now();
+        let scheduled = self.config.dnd.schedule.iter().any(|w| w.contains(&now));
+        if scheduled != self.dnd_scheduled {
+            self.dnd_scheduled = scheduled;
+            self.dnd_override = None;
+        }
+
+        let active = self.dnd_override.unwrap_or(scheduled);
+        if self.dnd_active && !active {
+            self.dnd_active = false;
+            self.show_dnd_summary();
+        }
+        self.dnd_active = active;
+    }
+
+    fn show_dnd_summary(&mut self) {
+        let held = std::mem::take(&mut self.dnd_held);
+        if held.total == 0 {
+            return;
+        }
+        self.show_interrupt(held.text());
     }
 
     fn on_tick(&mut self) {
@@ -156,58 +473,64 @@ impl App {
         } else {
             self.width
         };
-        
-        if let Some(ref text) = self.interrupt_text {
-            let elapsed = self.config.scroll_speed_ms as usize;
-            if self.interrupt_remaining_ms > elapsed {
-                self.interrupt_remaining_ms -= elapsed;
-            } else {
-                self.interrupt_text = None;
-                self.paused = self.paused_before_interrupt;
-                self.scroll_offset = self.saved_scroll_offset;
-                return;
-            }
-            
-            let seconds = (self.interrupt_remaining_ms as f64 / 1000.0).ceil() as usize;
-            let display_text = format!("({}s)  {}", seconds, text);
 
-             if display_text.width() > width {
-                 self.scroll_offset += 1;
-             }
-             return;
+        if self.ticker.tick(self.config.scroll_speed_ms as usize, width) {
+            self.paused = self.paused_before_interrupt;
         }
+    }
 
-        if self.text.width() > width {
-             self.scroll_offset += 1;
-        } else {
-             self.scroll_offset = 0;
+    /// Enables or disables the source selected in the status panel.
+    fn toggle_selected_source(&mut self) {
+        if let Some((name, health)) = self.health.snapshot().get(self.status_selected) {
+            self.health.set_enabled(name, !health.enabled);
         }
     }
 
-    fn handle_key(&mut self, key: KeyEvent, events: &EventHandler) {
+    fn handle_key(&mut self, key: KeyEvent) {
         match key.code {
             KeyCode::Char('q') | KeyCode::Esc => self.running = false,
-            KeyCode::Enter => {
-                if self.interrupt_text.is_some() {
-                    self.interrupt_text = None;
-                    self.paused = self.paused_before_interrupt;
-                    self.scroll_offset = self.saved_scroll_offset;
-                }
+            // Raw mode turns Ctrl-C and Ctrl-Z into key presses
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.running = false,
+            KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.suspend_requested = true;
             }
+            KeyCode::Enter if self.ticker.interrupt.is_some() => self.dismiss_interrupt(),
             KeyCode::Char(' ') => self.paused = !self.paused,
             KeyCode::Char('f') => self.config.show_frame = !self.config.show_frame,
-            KeyCode::Char('b') => self.dimmed = !self.dimmed,
-            KeyCode::Char('+') | KeyCode::Char('k') => {
-                if self.config.scroll_speed_ms > 10 {
-                    self.config.scroll_speed_ms -= 10;
-                    events.set_tick_rate(self.config.scroll_speed_ms);
-                }
+            KeyCode::Char('b') => {
+                self.dimmed = !self.dimmed;
+                self.update_styles();
             }
-            KeyCode::Char('-') | KeyCode::Char('j') => {
-                if self.config.scroll_speed_ms < 2000 {
-                    self.config.scroll_speed_ms += 10;
-                    events.set_tick_rate(self.config.scroll_speed_ms);
-                }
+            KeyCode::Char('h') => {
+                self.show_history = !self.show_history;
+                self.ticker.offset = 0;
+                self.sync_ticker_content();
+            }
+            KeyCode::Char('d') => {
+                self.dnd_override = Some(!self.dnd_active);
+                self.update_dnd();
+            }
+            KeyCode::Char('s') => {
+                self.show_status = !self.show_status;
+                self.show_logs = false;
+            }
+            KeyCode::Char('l') => {
+                self.show_logs = !self.show_logs;
+                self.show_status = false;
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.show_status => {
+                self.status_selected = self.status_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') if self.show_status => {
+                let count = self.health.snapshot().len();
+                self.status_selected = (self.status_selected + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Char('e') if self.show_status => self.toggle_selected_source(),
+            KeyCode::Char('+') | KeyCode::Char('k') if self.config.scroll_speed_ms > 10 => {
+                self.config.scroll_speed_ms -= 10;
+            }
+            KeyCode::Char('-') | KeyCode::Char('j') if self.config.scroll_speed_ms < 2000 => {
+                self.config.scroll_speed_ms += 10;
             }
             _ => {}
         }
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use crate::config::BackoffConfig;
+
+/// Exponential backoff with jitter for retrying failed requests.
+///
+/// The n-th consecutive delay is `initial * multiplier^(n-1)`, capped at
+/// `max`, and then randomized to between half and all of that value so that
+/// sources failing together do not retry in lockstep.
+#[derive(Debug)]
+pub struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(config: BackoffConfig) -> Self {
+        Self { config, attempt: 0 }
+    }
+
+    /// Returns the delay before the next retry and advances the backoff.
+    pub fn next_delay(&mut self) -> Duration {
+        let initial = self.config.initial_sec.max(1) as f64;
+        let max = self.config.max_sec.max(self.config.initial_sec.max(1)) as f64;
+        let exponent = self.attempt.min(63) as i32;
+        self.attempt = self.attempt.saturating_add(1);
+
+        let delay = (initial * self.config.multiplier.max(1.0).powi(exponent)).min(max);
+        Duration::from_secs_f64(delay * (0.5 + fastrand::f64() * 0.5))
+    }
+
+    /// Starts over after a success.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backoff(initial_sec: u64, max_sec: u64, multiplier: f64) -> Backoff {
+        Backoff::new(BackoffConfig {
+            initial_sec,
+            max_sec,
+            multiplier,
+        })
+    }
+
+    /// Asserts that the next delay is between half and all of `secs`.
+    fn assert_next(backoff: &mut Backoff, secs: f64) {
+        let delay = backoff.next_delay().as_secs_f64();
+        assert!(secs / 2.0 <= delay && delay <= secs, "{} not within {}", delay, secs);
+    }
+
+    #[test]
+    fn delays_grow_up_to_the_maximum() {
+        let mut backoff = backoff(2, 20, 3.0);
+        for secs in [2.0, 6.0, 18.0, 20.0, 20.0] {
+            assert_next(&mut backoff, secs);
+        }
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut backoff = backoff(5, 600, 2.0);
+        for _ in 0..10 {
+            backoff.next_delay();
+        }
+        backoff.reset();
+        assert_next(&mut backoff, 5.0);
+        assert_next(&mut backoff, 10.0);
+    }
+
+    #[test]
+    fn odd_settings_are_clamped() {
+        // No zero delays, no shrinking, and the maximum is at least the initial delay
+        let mut zero = backoff(0, 0, 0.5);
+        for _ in 0..3 {
+            assert_next(&mut zero, 1.0);
+        }
+        let mut inverted = backoff(30, 10, 2.0);
+        assert_next(&mut inverted, 30.0);
+        assert_next(&mut inverted, 30.0);
+    }
+
+    #[test]
+    fn many_failures_do_not_overflow() {
+        let mut backoff = backoff(1, 600, 10.0);
+        for _ in 0..200 {
+            assert!(backoff.next_delay() <= Duration::from_secs(600));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::path::PathBuf;
+use tracing::level_filters::LevelFilter;
+
+const USAGE: &str = "\
+Usage: infotube [OPTIONS]
+
+Options:
+  --log-level <LEVEL>    Log level: error, warn, info, debug, trace or off [default: info]
+  --record <FILE>        Record all events to FILE (JSON Lines)
+  --replay <FILE>        Replay events recorded with --record instead of running the sources
+  --replay-speed <N>     Replay speed multiplier [default: 1]
+  --status-line          Print the ticker as one line of text (e.g. for the tmux status bar) instead of running the UI
+  --width <N>            Width of the status line in columns [default: 40]
+  --follow               Keep running and print a new status line whenever it changes
+  --tmux                 Color alerts in the status line with tmux style codes
+  -h, --help             Print help";
+
+/// Command line options.
+#[derive(Debug)]
+pub struct Args {
+    pub log_level: LevelFilter,
+    /// File to record events to.
+    pub record: Option<PathBuf>,
+    /// Recording to replay instead of running the sources.
+    pub replay: Option<PathBuf>,
+    pub replay_speed: f64,
+    /// Print the ticker as text instead of running the UI.
+    pub status_line: bool,
+    /// Width of the status line, in columns.
+    pub width: u16,
+    /// Keep printing the status line instead of printing it once.
+    pub follow: bool,
+    /// Add tmux style codes to the status line.
+    pub tmux: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            log_level: LevelFilter::INFO,
+            record: None,
+            replay: None,
+            replay_speed: 1.0,
+            status_line: false,
+            width: 40,
+            follow: false,
+            tmux: false,
+        }
+    }
+}
+
+impl Args {
+    /// Parses the process arguments. Prints the usage and exits on `--help`.
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--opt value` and `--opt=value`
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("{} requires a value\n\n{}", name, USAGE))
+            };
+
+            match name.as_str() {
+                "--log-level" => {
+                    let level = value()?;
+                    parsed.log_level = level
+                        .parse()
+                        .map_err(|_| anyhow!("invalid log level '{}'\n\n{}", level, USAGE))?;
+                }
+                "--record" => parsed.record = Some(value()?.into()),
+                "--replay" => parsed.replay = Some(value()?.into()),
+                "--replay-speed" => {
+                    let speed = value()?;
+                    parsed.replay_speed = speed
+                        .parse()
+                        .ok()
+                        .filter(|speed: &f64| speed.is_finite() && *speed > 0.0)
+                        .ok_or_else(|| anyhow!("invalid replay speed '{}'\n\n{}", speed, USAGE))?;
+                }
+                "--status-line" => parsed.status_line = true,
+                "--width" => {
+                    let width = value()?;
+                    parsed.width = width
+                        .parse()
+                        .ok()
+                        .filter(|width: &u16| *width > 0)
+                        .ok_or_else(|| anyhow!("invalid width '{}'\n\n{}", width, USAGE))?;
+                }
+                "--follow" => parsed.follow = true,
+                "--tmux" => parsed.tmux = true,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => bail!("unknown argument '{}'\n\n{}", name, USAGE),
+            }
+        }
+        if parsed.status_line && parsed.replay.is_some() {
+            bail!("--replay cannot be used with --status-line\n\n{}", USAGE);
+        }
+        Ok(parsed)
+    }
+}
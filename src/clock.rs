@@ -0,0 +1,137 @@
+use chrono::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of the current time for the app and the sources.
+///
+/// Wall-clock time (schedules, timestamps) and instants (rate-limit windows)
+/// are read through a `Clock` so that tests can control them. Sleeps use
+/// tokio's timer, which tests can pause with `tokio::time::pause`.
+/// Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Default)]
+enum Kind {
+    /// The real time.
+    #[default]
+    System,
+    /// Moves only when advanced.
+    Manual(Arc<Mutex<ManualTime>>),
+    /// Moves with tokio's time, which stands still while paused.
+    Tokio {
+        start: DateTime<Local>,
+        origin: tokio::time::Instant,
+    },
+}
+
+#[derive(Debug)]
+struct ManualTime {
+    now: DateTime<Local>,
+    instant: Instant,
+}
+
+impl Clock {
+    /// The real time.
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// A clock set to `start` that only moves with `advance` and `set`.
+    pub fn manual(start: DateTime<Local>) -> Self {
+        Self {
+            kind: Kind::Manual(Arc::new(Mutex::new(ManualTime {
+                now: start,
+                instant: Instant::now(),
+            }))),
+        }
+    }
+
+    /// A clock set to `start` that moves with tokio's time. Under
+    /// `tokio::time::pause` it follows the virtual time, so that schedules
+    /// and sleeps agree.
+    pub fn tokio(start: DateTime<Local>) -> Self {
+        Self {
+            kind: Kind::Tokio {
+                start,
+                origin: tokio::time::Instant::now(),
+            },
+        }
+    }
+
+    /// The current local time.
+    pub fn now(&self) -> DateTime<Local> {
+        match &self.kind {
+            Kind::System => Local::now(),
+            Kind::Manual(time) => lock(time).now,
+            Kind::Tokio { start, origin } => *start + origin.elapsed(),
+        }
+    }
+
+    /// The current instant, for measuring durations.
+    pub fn instant(&self) -> Instant {
+        match &self.kind {
+            Kind::System => Instant::now(),
+            Kind::Manual(time) => lock(time).instant,
+            Kind::Tokio { .. } => tokio::time::Instant::now().into_std(),
+        }
+    }
+
+    /// Moves a manual clock forward. Other clocks are not affected.
+    pub fn advance(&self, duration: Duration) {
+        if let Kind::Manual(time) = &self.kind {
+            let mut time = lock(time);
+            time.now += duration;
+            time.instant += duration;
+        }
+    }
+
+    /// Sets the time of a manual clock, moving its instant by the same amount
+    /// if the time moves forward. Other clocks are not affected.
+    pub fn set(&self, now: DateTime<Local>) {
+        if let Kind::Manual(time) = &self.kind {
+            let mut time = lock(time);
+            if let Ok(forward) = (now - time.now).to_std() {
+                time.instant += forward;
+            }
+            time.now = now;
+        }
+    }
+}
+
+fn lock(time: &Mutex<ManualTime>) -> std::sync::MutexGuard<'_, ManualTime> {
+    time.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn start() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 1, 6, 9, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn manual_clock_moves_only_when_advanced() {
+        let clock = Clock::manual(start());
+        let instant = clock.instant();
+        assert_eq!(clock.now(), start());
+
+        clock.clone().advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), Local.with_ymd_and_hms(2025, 1, 6, 9, 1, 30).unwrap());
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+
+        clock.set(Local.with_ymd_and_hms(2025, 1, 6, 10, 0, 0).unwrap());
+        assert_eq!(clock.instant() - instant, Duration::from_secs(3600));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tokio_clock_follows_paused_time() {
+        let clock = Clock::tokio(start());
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_eq!(clock.now(), Local.with_ymd_and_hms(2025, 1, 6, 10, 0, 0).unwrap());
+    }
+}
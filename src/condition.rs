@@ -0,0 +1,248 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use crate::event::{Message, Priority, Route};
+use crate::json::{self, Path};
+
+/// Comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Op {
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+}
+
+/// A condition on the values of a JSON payload.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    /// Holds if every inner condition holds.
+    All { all: Vec<Condition> },
+    /// Holds if at least one inner condition holds.
+    Any { any: Vec<Condition> },
+    /// Compares the value at `path` with `value`.
+    Compare { path: Path, op: Op, value: Value },
+}
+
+impl Condition {
+    /// Evaluates the condition. A comparison holds if any value selected by
+    /// its path satisfies it; comparisons on missing fields never hold.
+    pub fn eval(&self, json: &Value) -> bool {
+        match self {
+            Condition::All { all } => all.iter().all(|c| c.eval(json)),
+            Condition::Any { any } => any.iter().any(|c| c.eval(json)),
+            Condition::Compare { path, op, value } => {
+                path.select(json).iter().any(|actual| {
+                    let Some(ordering) = json::compare_values(actual, value) else {
+                        return false;
+                    };
+                    match op {
+                        Op::Eq => ordering == Ordering::Equal,
+                        Op::Ne => ordering != Ordering::Equal,
+                        Op::Gt => ordering == Ordering::Greater,
+                        Op::Ge => ordering != Ordering::Less,
+                        Op::Lt => ordering == Ordering::Less,
+                        Op::Le => ordering != Ordering::Greater,
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// What happens to a message whose threshold condition does not hold.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Otherwise {
+    /// Discard the message.
+    #[default]
+    Drop,
+    /// Show it in the normal ticker rotation instead of interrupting.
+    Ticker,
+}
+
+/// Threshold settings of an API or WebSocket source.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Threshold {
+    /// Condition on the JSON payload
+    pub when: Condition,
+    /// Handling of messages that do not meet the condition
+    #[serde(default)]
+    pub otherwise: Otherwise,
+    /// Priority given to messages that meet the condition
+    pub priority: Option<Priority>,
+}
+
+impl Threshold {
+    /// Applies the threshold to a message. Returns `None` if it is dropped.
+    /// Messages without a JSON payload never meet the condition.
+    pub fn apply(&self, mut msg: Message) -> Option<Message> {
+        let met = msg.json.as_ref().is_some_and(|json| self.when.eval(json));
+        if met {
+            if let Some(priority) = self.priority {
+                msg.priority = priority;
+            }
+            return Some(msg);
+        }
+
+        match self.otherwise {
+            Otherwise::Drop => None,
+            Otherwise::Ticker => {
+                msg.route = Route::Ticker;
+                Some(msg)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn condition(toml: &str) -> Condition {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn holds(toml: &str, json: Value) -> bool {
+        condition(toml).eval(&json)
+    }
+
+    fn compare(op: &str, value: &str, json: Value) -> bool {
+        holds(&format!("path = \"$.mag\"\nop = \"{}\"\nvalue = {}", op, value), json)
+    }
+
+    #[test]
+    fn operators_compare_numbers() {
+        let cases = [
+            ("==", [false, true, false]),
+            ("!=", [true, false, true]),
+            (">", [false, false, true]),
+            (">=", [false, true, true]),
+            ("<", [true, false, false]),
+            ("<=", [true, true, false]),
+        ];
+        for (op, expected) in cases {
+            let actual = [4.9, 5.0, 5.1].map(|mag| compare(op, "5", json!({ "mag": mag })));
+            assert_eq!(actual, expected, "{}", op);
+        }
+    }
+
+    #[test]
+    fn numeric_strings_compare_as_numbers() {
+        assert!(!compare(">=", "10", json!({ "mag": "9.5" })));
+        assert!(compare("==", "5", json!({ "mag": " 5.0 " })));
+        assert!(compare("<", "\"10\"", json!({ "mag": 9 })));
+    }
+
+    #[test]
+    fn other_values_compare_as_text() {
+        assert!(compare("==", "\"warning\"", json!({ "mag": "warning" })));
+        assert!(compare("!=", "\"warning\"", json!({ "mag": "Warning" })));
+        assert!(compare(">", "\"a\"", json!({ "mag": "b" })));
+        assert!(compare("==", "true", json!({ "mag": true })));
+        // Text is not a number: "abc" sorts after "5"
+        assert!(compare(">", "5", json!({ "mag": "abc" })));
+        assert!(!compare("<", "5", json!({ "mag": "abc" })));
+    }
+
+    #[test]
+    fn missing_and_null_values_never_match() {
+        for op in ["==", "!=", ">", ">=", "<", "<="] {
+            assert!(!compare(op, "5", json!({ "depth": 5 })), "{}", op);
+            assert!(!compare(op, "5", json!({ "mag": null })), "{}", op);
+        }
+    }
+
+    #[test]
+    fn any_selected_value_may_match() {
+        let toml = "path = \"$.quakes[*].mag\"\nop = \">=\"\nvalue = 6";
+        assert!(holds(toml, json!({ "quakes": [{ "mag": 3 }, { "mag": 6.2 }] })));
+        assert!(!holds(toml, json!({ "quakes": [{ "mag": 3 }, { "mag": 4 }] })));
+        assert!(!holds(toml, json!({ "quakes": [] })));
+    }
+
+    #[test]
+    fn all_and_any_combine_conditions() {
+        let toml = r#"
+            any = [
+                { path = "$.mag", op = ">=", value = 6 },
+                { all = [
+                    { path = "$.mag", op = ">=", value = 4 },
+                    { path = "$.depth", op = "<", value = 10 },
+                ] },
+            ]
+        "#;
+        assert!(holds(toml, json!({ "mag": 6.5, "depth": 50 })));
+        assert!(holds(toml, json!({ "mag": 4.5, "depth": 5 })));
+        assert!(!holds(toml, json!({ "mag": 4.5, "depth": 50 })));
+        assert!(!holds(toml, json!({ "mag": 3, "depth": 5 })));
+
+        // Empty lists hold vacuously for `all` and never for `any`
+        assert!(holds("all = []", json!({})));
+        assert!(!holds("any = []", json!({})));
+    }
+
+    #[test]
+    fn unknown_operators_are_rejected() {
+        assert!(toml::from_str::<Condition>("path = \"$.mag\"\nop = \"=~\"\nvalue = 5").is_err());
+    }
+
+    fn threshold(otherwise: &str) -> Threshold {
+        toml::from_str(&format!(
+            r#"
+            when = {{ path = "$.mag", op = ">=", value = 5 }}
+            priority = "critical"
+            {}
+            "#,
+            otherwise
+        ))
+        .unwrap()
+    }
+
+    fn message(json: Option<Value>) -> Message {
+        let mut msg = Message::new("Quake", "M5", Priority::Normal);
+        msg.json = json;
+        msg
+    }
+
+    #[test]
+    fn threshold_raises_the_priority_when_met() {
+        let msg = threshold("").apply(message(Some(json!({ "mag": 5 })))).unwrap();
+        assert_eq!(msg.priority, Priority::Critical);
+        assert_eq!(msg.route, Route::Interrupt);
+
+        let mut keep = threshold("");
+        keep.priority = None;
+        let msg = keep.apply(message(Some(json!({ "mag": 5 })))).unwrap();
+        assert_eq!(msg.priority, Priority::Normal);
+    }
+
+    #[test]
+    fn threshold_drops_by_default() {
+        let threshold = threshold("");
+        assert_eq!(threshold.otherwise, Otherwise::Drop);
+        assert!(threshold.apply(message(Some(json!({ "mag": 4 })))).is_none());
+        assert!(threshold.apply(message(None)).is_none());
+    }
+
+    #[test]
+    fn threshold_can_send_the_rest_to_the_ticker() {
+        let threshold = threshold("otherwise = \"ticker\"");
+        for json in [Some(json!({ "mag": 4 })), None] {
+            let msg = threshold.apply(message(json)).unwrap();
+            assert_eq!(msg.route, Route::Ticker);
+            assert_eq!(msg.priority, Priority::Normal);
+        }
+    }
+}
@@ -1,7 +1,18 @@
 use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result, bail};
+
+use crate::condition::Threshold;
+use crate::event::Priority;
+use crate::interpolate;
+use crate::json;
+use crate::queue::OverflowPolicy;
+use crate::rules::Rule;
+use crate::schedule::{CronSchedule, TimeWindow};
+use crate::template::MessageFormat;
 
 /// config.tomlの構造を定義する構造体
 #[derive(Debug, Deserialize, Clone)]
@@ -29,6 +40,21 @@ pub struct Config {
     /// WebSocketソースのリスト
     #[serde(default)]
     pub ws_sources: Vec<WsConfig>,
+    /// TCPリスナーの設定
+    #[serde(default)]
+    pub listener: ListenerConfig,
+    /// おやすみモード (Do Not Disturb) の設定
+    #[serde(default)]
+    pub dnd: DndConfig,
+    /// メッセージの絞り込み・振り分け・書き換えルール (上から順に適用)
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// 外部メッセージのキューの設定
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// SIGHUP を受けたときに終了せず設定を再読み込みする
+    #[serde(default)]
+    pub reload_on_sighup: bool,
 }
 
 fn default_show_frame() -> bool {
@@ -64,17 +90,118 @@ pub struct ApiConfig {
     /// ポーリング間隔 (秒)
     #[serde(default = "default_interval")]
     pub interval_sec: u64,
-    /// JSONレスポンスから抽出するキーのパス (例: ["data", "message"])
-    pub json_keys: Option<Vec<String>>,
+    /// cron形式のスケジュール (指定時は interval_sec の代わりに使用。例: "*/5 9-18 * * mon-fri")
+    pub cron: Option<CronSchedule>,
+    /// 定期のリクエストをずらす最大の遅延 (秒。起動直後・有効化直後・再試行には加えない)
+    #[serde(default)]
+    pub jitter_sec: u64,
+    /// リクエストを行う時間帯 (空の場合は常に)
+    #[serde(default)]
+    pub active_hours: Vec<TimeWindow>,
+    /// HTTPメソッド (例: "GET", "POST"。省略時は GET)
+    pub method: Option<String>,
+    /// リクエストヘッダー (値は文字列、{ env = "VAR" } または { file = "path" })
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+    /// クエリパラメータ
+    #[serde(default)]
+    pub query: BTreeMap<String, String>,
+    /// リクエストボディ ({ json = {...} }, { form = {...} } または { text = "..." })
+    pub body: Option<RequestBody>,
+    /// 認証設定 ({ type = "bearer", token = ... } または { type = "basic", username = ..., password = ... })
+    pub auth: Option<Auth>,
+    /// リクエストのタイムアウト (秒)
+    #[serde(default = "default_timeout")]
+    pub timeout_sec: u64,
+    /// レスポンスボディの最大サイズ (バイト、展開後)
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// 失敗時の再試行間隔の設定
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+    /// JSONレスポンスから表示テキストを組み立てる設定 (json_keys / fields / template)
+    #[serde(flatten)]
+    pub format: MessageFormat,
     /// 有効/無効
     #[serde(default = "default_api_enabled")]
     pub enabled: bool,
+    /// メッセージの優先度 (low / normal / high / critical)
+    #[serde(default)]
+    pub priority: Priority,
+    /// 流量制限と重複抑制の設定
+    pub rate_limit: Option<RateLimitConfig>,
+    /// 抽出した値に対する条件 (満たしたときだけ割り込み表示する)
+    pub threshold: Option<Threshold>,
+    /// 配列を選択するパス (指定時は新しい要素ごとに1件のメッセージを表示)
+    pub items: Option<json::Path>,
+    /// 要素の重複判定に使うIDのパス (省略時は要素の内容全体で判定)
+    pub id_field: Option<json::Path>,
 }
 
 fn default_interval() -> u64 {
     300
 }
 
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_max_body_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
+/// 秘密情報などの値。設定ファイルに直接書くほか、環境変数やファイルから読み込める
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Secret {
+    /// 値をそのまま指定
+    Plain(String),
+    /// 環境変数から読み込む
+    Env { env: String },
+    /// ファイルから読み込む (前後の空白は除去)
+    File { file: String },
+}
+
+impl Secret {
+    /// 値を取得する
+    pub fn resolve(&self) -> Result<String> {
+        match self {
+            Secret::Plain(value) => Ok(value.clone()),
+            Secret::Env { env } => std::env::var(env)
+                .with_context(|| format!("environment variable {} is not set", env)),
+            Secret::File { file } => {
+                let path = expand_path(file);
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read secret file {:?}", path))?;
+                Ok(content.trim().to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestBody {
+    /// JSONボディ
+    Json(Value),
+    /// フォーム (application/x-www-form-urlencoded)
+    Form(BTreeMap<String, String>),
+    /// テキスト
+    Text(String),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    /// Bearerトークン認証
+    Bearer { token: Secret },
+    /// Basic認証
+    Basic {
+        username: String,
+        password: Option<Secret>,
+    },
+}
+
 fn default_api_enabled() -> bool {
     true
 }
@@ -85,20 +212,204 @@ pub struct WsConfig {
     pub name: String,
     /// 接続先URL (wss://...)
     pub url: String,
-    /// JSONから抽出するキーのパス
-    pub json_keys: Option<Vec<String>>,
+    /// JSONから表示テキストを組み立てる設定 (json_keys / fields / template)
+    #[serde(flatten)]
+    pub format: MessageFormat,
     /// 有効/無効
     #[serde(default = "default_api_enabled")]
     pub enabled: bool,
+    /// メッセージの優先度 (low / normal / high / critical)
+    #[serde(default)]
+    pub priority: Priority,
+    /// 流量制限と重複抑制の設定
+    pub rate_limit: Option<RateLimitConfig>,
+    /// 抽出した値に対する条件 (満たしたときだけ割り込み表示する)
+    pub threshold: Option<Threshold>,
+    /// 配列を選択するパス (指定時は新しい要素ごとに1件のメッセージを表示)
+    pub items: Option<json::Path>,
+    /// 要素の重複判定に使うIDのパス (省略時は要素の内容全体で判定)
+    pub id_field: Option<json::Path>,
+    /// 切断・接続失敗時の再接続間隔の設定
+    #[serde(default)]
+    pub backoff: BackoffConfig,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ListenerConfig {
+    /// JSONを受信したときに表示テキストを組み立てる設定 (json_keys / fields / template)
+    #[serde(flatten)]
+    pub format: MessageFormat,
+    /// 受信したメッセージの優先度
+    #[serde(default)]
+    pub priority: Priority,
+    /// 流量制限と重複抑制の設定
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+/// 失敗が続いたときの再試行間隔 (指数的に増加し、ランダムなゆらぎを加える)
+#[derive(Debug, Deserialize, Clone)]
+pub struct BackoffConfig {
+    /// 最初の再試行までの待ち時間 (秒)
+    #[serde(default = "default_backoff_initial")]
+    pub initial_sec: u64,
+    /// 待ち時間の上限 (秒)
+    #[serde(default = "default_backoff_max")]
+    pub max_sec: u64,
+    /// 失敗ごとに待ち時間を何倍にするか
+    #[serde(default = "default_backoff_multiplier")]
+    pub multiplier: f64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_sec: default_backoff_initial(),
+            max_sec: default_backoff_max(),
+            multiplier: default_backoff_multiplier(),
+        }
+    }
+}
+
+fn default_backoff_initial() -> u64 {
+    5
+}
+
+fn default_backoff_max() -> u64 {
+    600
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// ウィンドウ内に表示する最大メッセージ数 (省略時は無制限)
+    pub max_messages: Option<usize>,
+    /// 流量を数えるウィンドウの長さ (秒)
+    #[serde(default = "default_rate_window")]
+    pub window_sec: u64,
+    /// 同一メッセージを抑制する期間 (秒)。抑制中に同じメッセージが届くと、そこから数え直す
+    pub dedup_ttl_sec: Option<u64>,
+    /// 超過分を捨てずに「N updates」の要約1件にまとめる (max_messages が必要)
+    #[serde(default)]
+    pub coalesce: bool,
+}
+
+fn default_rate_window() -> u64 {
+    60
+}
+
+/// 外部メッセージ (API / WebSocket / TCPリスナー) を表示処理に渡すキュー
+#[derive(Debug, Deserialize, Clone)]
+pub struct QueueConfig {
+    /// キューに溜められる最大メッセージ数
+    #[serde(default = "default_queue_capacity")]
+    pub capacity: usize,
+    /// キューが一杯のときの扱い (drop_oldest / drop_newest / coalesce)
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: default_queue_capacity(),
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+fn default_queue_capacity() -> usize {
+    1000
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DndConfig {
+    /// 自動的におやすみモードにする時間帯のリスト
+    #[serde(default)]
+    pub schedule: Vec<TimeWindow>,
+    /// おやすみモード中でも割り込み表示する最低優先度
+    #[serde(default = "default_dnd_min_priority")]
+    pub min_priority: Priority,
+}
+
+fn default_dnd_min_priority() -> Priority {
+    Priority::Critical
+}
+
+impl Default for DndConfig {
+    fn default() -> Self {
+        Self {
+            schedule: vec![],
+            min_priority: default_dnd_min_priority(),
+        }
+    }
+}
+
+/// 先頭の `~` をホームディレクトリに展開する
+pub fn expand_path(path_str: &str) -> PathBuf {
+    if let Some(home) = dirs::home_dir() {
+        if path_str == "~" {
+            return home;
+        } else if let Some(rest) = path_str.strip_prefix("~/") {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path_str)
+}
+
+/// 設定ファイルのパス (~/.config/infotube/config.toml)
+pub fn config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".config/infotube/config.toml"))
 }
 
 impl Config {
     /// ファイルから設定を読み込む
+    ///
+    /// 文字列中の `${VAR}` / `${VAR:-default}` と `file:PATH` は読み込み時に解決する
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
         let content = fs::read_to_string(path)?;
-        let config = toml::from_str(&content)?;
+        let mut value = toml::Value::Table(
+            toml::from_str(&content).with_context(|| format!("failed to parse {:?}", path))?,
+        );
+        interpolate::resolve(&mut value)
+            .with_context(|| format!("failed to resolve references in {:?}", path))?;
+        let config: Self = value
+            .try_into()
+            .with_context(|| format!("invalid config in {:?}", path))?;
+        config
+            .validate()
+            .with_context(|| format!("invalid config in {:?}", path))?;
         Ok(config)
     }
+
+    /// 項目の組み合わせを検証する
+    fn validate(&self) -> Result<()> {
+        let limits = self
+            .api_sources
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (format!("api_sources[{}]", i), &c.rate_limit))
+            .chain(
+                self.ws_sources
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| (format!("ws_sources[{}]", i), &c.rate_limit)),
+            )
+            .chain(std::iter::once(("listener".to_string(), &self.listener.rate_limit)));
+
+        for (key, limit) in limits {
+            if let Some(limit) = limit
+                && limit.coalesce
+                && limit.max_messages.is_none()
+            {
+                bail!("{}.rate_limit: coalesce requires max_messages", key);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for Config {
@@ -114,12 +425,51 @@ impl Default for Config {
             colors: Colors {
                 fg_default: "White".to_string(),
                 bg_default: "None".to_string(),
-                                fg_alert: "Red".to_string(),
-                                bg_alert: "None".to_string(),
-                                        },
-                                        api_sources: vec![],
-                                        ws_sources: vec![],
-                                    }
-                                }
-                            }
-                            
\ No newline at end of file
+                fg_alert: "Red".to_string(),
+                bg_alert: "None".to_string(),
+            },
+            api_sources: vec![],
+            ws_sources: vec![],
+            listener: ListenerConfig::default(),
+            dnd: DndConfig::default(),
+            rules: vec![],
+            queue: QueueConfig::default(),
+            reload_on_sighup: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limit(toml: &str) -> Option<RateLimitConfig> {
+        Some(toml::from_str(toml).unwrap())
+    }
+
+    #[test]
+    fn coalesce_requires_max_messages() {
+        let mut config = Config::default();
+        let mut api: ApiConfig = toml::from_str(
+            r#"
+            name = "Status"
+            url = "http://localhost/status"
+            json_keys = ["$.message"]
+            "#,
+        )
+        .unwrap();
+        api.rate_limit = rate_limit("max_messages = 3\ncoalesce = true");
+        config.api_sources = vec![api.clone(), api];
+        config.listener.rate_limit = rate_limit("dedup_ttl_sec = 60");
+        assert!(config.validate().is_ok());
+
+        config.api_sources[1].rate_limit = rate_limit("dedup_ttl_sec = 60\ncoalesce = true");
+        let error = config.validate().unwrap_err();
+        assert_eq!(error.to_string(), "api_sources[1].rate_limit: coalesce requires max_messages");
+
+        config.api_sources.clear();
+        config.listener.rate_limit = rate_limit("coalesce = true");
+        let error = config.validate().unwrap_err();
+        assert_eq!(error.to_string(), "listener.rate_limit: coalesce requires max_messages");
+    }
+}
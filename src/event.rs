@@ -1,51 +1,228 @@
 use crossterm::event::{Event as CrosstermEvent, KeyEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::clock::Clock;
+use crate::config::QueueConfig;
+use crate::health::HealthRegistry;
+use crate::queue::{MessageQueue, MessageSender};
+use crate::recording::{self, Recorder, Recording};
+use crate::shutdown::Shutdown;
+
+/// Capacity of the lane for ticks, keys, resizes and signals.
+const CONTROL_CAPACITY: usize = 64;
+/// Period (ms) of the tick timer when it is disabled during a replay.
+const DEFAULT_TICK_RATE: u64 = 100;
+
+/// Priority of an external message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+/// Where a message is displayed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Route {
+    /// Take over the screen as an interrupt.
+    #[default]
+    Interrupt,
+    /// Join the normal ticker rotation.
+    Ticker,
+}
+
+/// Colors overriding the configured alert colors for one message.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageStyle {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+}
+
+/// External message (e.g. from TCP, API or WebSocket).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Message {
+    /// Name of the source that produced the message.
+    pub source: String,
+    /// Text to display.
+    pub text: String,
+    /// Priority of the message.
+    #[serde(default)]
+    pub priority: Priority,
+    /// JSON payload the text was extracted from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+    /// Where the message is displayed.
+    #[serde(default)]
+    pub route: Route,
+    /// Style override for the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<MessageStyle>,
+    /// Sound override for the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+}
+
+impl Message {
+    /// Constructs a new message.
+    pub fn new(source: impl Into<String>, text: impl Into<String>, priority: Priority) -> Self {
+        Self {
+            source: source.into(),
+            text: text.into(),
+            priority,
+            json: None,
+            route: Route::default(),
+            style: None,
+            sound: None,
+        }
+    }
+
+    /// Constructs a message whose text is labeled with the source name, as
+    /// `[source] text`.
+    pub fn labeled(source: impl Into<String>, text: impl AsRef<str>, priority: Priority) -> Self {
+        let source = source.into();
+        let text = format!("[{}] {}", source, text.as_ref());
+        Self::new(source, text, priority)
+    }
+
+    /// Attaches the JSON payload the message was extracted from.
+    pub fn with_json(mut self, json: Value) -> Self {
+        self.json = Some(json);
+        self
+    }
+}
+
+/// Process signals handled by the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Signal {
+    /// SIGTERM
+    Terminate,
+    /// SIGINT
+    Interrupt,
+    /// SIGHUP (e.g. the tmux pane was closed)
+    Hangup,
+}
 
 /// Terminal events.
-#[derive(Clone, Debug)]
+///
+/// Serialized for recordings as `"tick"`, `{"key": ...}`, `{"message": ...}`,
+/// `{"resize": 80}` and `{"signal": "hangup"}`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Event {
     /// Terminal tick.
     Tick,
     /// Key press.
     Key(KeyEvent),
     /// External message (e.g. from TCP).
-    Message(String),
+    Message(Message),
     /// Terminal resize.
     Resize(u16),
+    /// Signal received by the process.
+    Signal(Signal),
 }
 
 /// Event handler.
+///
+/// Events arrive on two bounded lanes: ticks, keys, resizes and signals on
+/// the control lane, and external messages on the message queue. The control
+/// lane is always served first, so a flood of messages cannot delay input.
 #[derive(Debug)]
 pub struct EventHandler {
-    /// Event receiver channel.
-    rx: mpsc::UnboundedReceiver<Event>,
-    /// Event sender channel (to clone for other tasks).
-    tx: mpsc::UnboundedSender<Event>,
+    /// Control lane receiver; closes when the input and signal tasks end.
+    rx: mpsc::Receiver<Event>,
+    /// External messages.
+    messages: MessageQueue,
     /// Sender to update the tick rate.
     tick_speed_tx: mpsc::UnboundedSender<u64>,
+    /// Writes the events returned by `next`, if recording.
+    recorder: Option<Recorder>,
 }
 
 impl EventHandler {
     /// Constructs a new instance of `EventHandler`.
-    pub fn new(tick_rate: u64) -> Self {
-        let (tx, rx) = mpsc::unbounded_channel();
+    /// Input and signal tasks stop when `shutdown` starts. Messages dropped
+    /// by the queue are counted in `health`.
+    pub fn new(
+        tick_rate: u64,
+        queue: &QueueConfig,
+        health: HealthRegistry,
+        shutdown: &Shutdown,
+    ) -> Self {
+        Self::with_input(Some(tick_rate), true, queue, health, shutdown).0
+    }
+
+    /// Constructs an `EventHandler` that does not read the terminal, for
+    /// running without one (e.g. the status line mode). Signals are still
+    /// received.
+    pub fn headless(
+        tick_rate: u64,
+        queue: &QueueConfig,
+        health: HealthRegistry,
+        shutdown: &Shutdown,
+    ) -> Self {
+        Self::with_input(Some(tick_rate), false, queue, health, shutdown).0
+    }
+
+    /// Constructs an `EventHandler` that replays `recording` (see
+    /// `recording::replay`) instead of generating ticks, setting `clock` to
+    /// the recorded time of each event. Keys and signals are still received,
+    /// so the replay can be paused or quit.
+    pub fn replay(
+        recording: Recording,
+        speed: f64,
+        clock: Clock,
+        queue: &QueueConfig,
+        health: HealthRegistry,
+        shutdown: &Shutdown,
+    ) -> Self {
+        let (events, tx) = Self::with_input(None, true, queue, health, shutdown);
+        shutdown.spawn_until_cancelled(recording::replay(recording, speed, clock, tx));
+        events
+    }
+
+    /// Starts the input and signal tasks, and the tick timer unless
+    /// `tick_rate` is `None`. Keys and resizes are read only if `terminal`.
+    /// Also returns a sender to the control lane.
+    fn with_input(
+        tick_rate: Option<u64>,
+        terminal: bool,
+        queue: &QueueConfig,
+        health: HealthRegistry,
+        shutdown: &Shutdown,
+    ) -> (Self, mpsc::Sender<Event>) {
+        let (tx, rx) = mpsc::channel(CONTROL_CAPACITY);
+        let messages = MessageQueue::new(queue.capacity, queue.overflow, health);
         let (tick_speed_tx, mut tick_speed_rx) = mpsc::unbounded_channel();
         let _tx = tx.clone();
         
         // Spawn a task to handle tick and key events
-        tokio::spawn(async move {
-            let mut reader = crossterm::event::EventStream::new();
-            let mut interval = tokio::time::interval(Duration::from_millis(tick_rate));
+        shutdown.spawn_until_cancelled(async move {
+            let mut reader = terminal.then(crossterm::event::EventStream::new);
+            let ticking = tick_rate.is_some();
+            let mut interval = tokio::time::interval(Duration::from_millis(tick_rate.unwrap_or(DEFAULT_TICK_RATE)));
             let mut last_tick = tokio::time::Instant::now();
 
             loop {
                 let tick_delay = interval.tick();
-                let crossterm_event = reader.next();
+                let crossterm_event = async {
+                    match &mut reader {
+                        Some(reader) => reader.next().await,
+                        None => std::future::pending().await,
+                    }
+                };
                 
                 tokio::select! {
                     // Update tick rate if requested
-                    Some(new_rate) = tick_speed_rx.recv() => {
+                    Some(new_rate) = tick_speed_rx.recv(), if ticking => {
                         let new_duration = Duration::from_millis(new_rate);
                         
                         let next_target = last_tick + new_duration;
@@ -57,34 +234,46 @@ impl EventHandler {
                             new_duration
                         );
                     }
-                    _ = tick_delay => {
+                    _ = tick_delay, if ticking => {
                         last_tick = tokio::time::Instant::now();
-                        if _tx.send(Event::Tick).is_err() {
+                        // A tick is skipped if the app has not caught up with the previous ones
+                        if let Err(mpsc::error::TrySendError::Closed(_)) = _tx.try_send(Event::Tick) {
                             break;
                         }
                     }
                     Some(Ok(evt)) = crossterm_event => {
-                        match evt {
-                            CrosstermEvent::Key(key) => {
-                                if key.kind == crossterm::event::KeyEventKind::Press {
-                                    if _tx.send(Event::Key(key)).is_err() {
-                                        break;
-                                    }
-                                }
+                        let event = match evt {
+                            CrosstermEvent::Key(key)
+                                if key.kind == crossterm::event::KeyEventKind::Press =>
+                            {
+                                Event::Key(key)
                             }
-                            CrosstermEvent::Resize(w, _h) => {
-                                if _tx.send(Event::Resize(w)).is_err() {
-                                    break;
-                                }
-                            }
-                            _ => {}
+                            CrosstermEvent::Resize(w, _h) => Event::Resize(w),
+                            _ => continue,
+                        };
+                        if _tx.send(event).await.is_err() {
+                            break;
                         }
                     }
                 }
             }
         });
 
-        Self { rx, tx, tick_speed_tx }
+        #[cfg(unix)]
+        spawn_signal_listener(tx.clone(), shutdown);
+
+        let events = Self {
+            rx,
+            messages,
+            tick_speed_tx,
+            recorder: None,
+        };
+        (events, tx)
+    }
+
+    /// Records every event returned by `next` from now on.
+    pub fn record_to(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
     }
 
     /// Set a new tick rate.
@@ -92,14 +281,55 @@ impl EventHandler {
         let _ = self.tick_speed_tx.send(tick_rate);
     }
 
-    /// Get a sender to the event channel.
-    pub fn sender(&self) -> mpsc::UnboundedSender<Event> {
-        self.tx.clone()
+    /// Get a sender to the message queue (to clone for other tasks).
+    pub fn sender(&self) -> MessageSender {
+        self.messages.sender()
     }
 
-    /// Receive the next event.
+    /// Receive the next event, preferring the control lane.
     pub async fn next(&mut self) -> Option<Event> {
-        self.rx.recv().await
+        let event = tokio::select! {
+            biased;
+            event = self.rx.recv() => event,
+            msg = self.messages.recv() => Some(Event::Message(msg)),
+        };
+
+        if let (Some(recorder), Some(event)) = (&mut self.recorder, &event)
+            && let Err(e) = recorder.record(event)
+        {
+            warn!(error = format!("{:#}", e), "failed to record event; recording stopped");
+            self.recorder = None;
+        }
+        event
     }
 }
 use futures::StreamExt;
+
+/// Forwards SIGTERM, SIGINT and SIGHUP to the event channel, so that the
+/// terminal is restored before the process exits.
+#[cfg(unix)]
+fn spawn_signal_listener(tx: mpsc::Sender<Event>, shutdown: &Shutdown) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    shutdown.spawn_until_cancelled(async move {
+        let (Ok(mut terminate), Ok(mut interrupt), Ok(mut hangup)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::interrupt()),
+            signal(SignalKind::hangup()),
+        ) else {
+            tracing::warn!("failed to install signal handlers");
+            return;
+        };
+
+        loop {
+            let signal = tokio::select! {
+                _ = terminate.recv() => Signal::Terminate,
+                _ = interrupt.recv() => Signal::Interrupt,
+                _ = hangup.recv() => Signal::Hangup,
+            };
+            if tx.send(Event::Signal(signal)).await.is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Local};
+use serde_json::{Map, Value, json};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::clock::Clock;
+
+/// Consecutive failures after which a degraded source is reported as failing.
+const FAILING_THRESHOLD: u32 = 3;
+
+/// Connection state of a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceState {
+    /// No request or connection has completed yet.
+    Starting,
+    /// The last request (or the current connection) succeeded.
+    Connected,
+    /// Recent attempts failed, but fewer than `FAILING_THRESHOLD` in a row.
+    Degraded,
+    /// At least `FAILING_THRESHOLD` attempts in a row failed.
+    Failing,
+}
+
+impl SourceState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceState::Starting => "starting",
+            SourceState::Connected => "connected",
+            SourceState::Degraded => "degraded",
+            SourceState::Failing => "failing",
+        }
+    }
+}
+
+/// Health of one source.
+#[derive(Debug, Clone)]
+pub struct SourceHealth {
+    /// Kind of source (`api`, `ws` or `listener`).
+    pub kind: &'static str,
+    /// Whether the source is running; can be toggled at runtime.
+    pub enabled: bool,
+    pub state: SourceState,
+    /// Failures since the last success.
+    pub consecutive_failures: u32,
+    /// Failures since startup.
+    pub error_count: u64,
+    /// Messages dropped because the message queue was full.
+    pub dropped: u64,
+    /// Time of the first failure of the current streak.
+    pub failing_since: Option<DateTime<Local>>,
+    pub last_success: Option<DateTime<Local>>,
+    pub last_error: Option<String>,
+    /// Time and text of the last message received from the source.
+    pub last_message: Option<DateTime<Local>>,
+    pub last_value: Option<String>,
+}
+
+impl SourceHealth {
+    fn new(kind: &'static str, enabled: bool) -> Self {
+        Self {
+            kind,
+            enabled,
+            state: SourceState::Starting,
+            consecutive_failures: 0,
+            error_count: 0,
+            dropped: 0,
+            failing_since: None,
+            last_success: None,
+            last_error: None,
+            last_message: None,
+            last_value: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let time = |t: &Option<DateTime<Local>>| t.map(|t| t.to_rfc3339());
+        json!({
+            "kind": self.kind,
+            "enabled": self.enabled,
+            "state": self.state.as_str(),
+            "consecutive_failures": self.consecutive_failures,
+            "error_count": self.error_count,
+            "dropped": self.dropped,
+            "failing_since": time(&self.failing_since),
+            "last_success": time(&self.last_success),
+            "last_error": self.last_error,
+            "last_message": time(&self.last_message),
+            "last_value": self.last_value,
+        })
+    }
+}
+
+/// Shared health state of all network sources, updated by the source tasks
+/// and read by the UI and the status endpoint. Sources are kept in
+/// registration order.
+#[derive(Debug, Clone, Default)]
+pub struct HealthRegistry {
+    sources: Arc<Mutex<Vec<(String, SourceHealth)>>>,
+    /// Signalled whenever a source is enabled or disabled.
+    toggled: Arc<Notify>,
+    clock: Clock,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry that timestamps events with `clock`.
+    pub fn with_clock(clock: Clock) -> Self {
+        Self {
+            clock,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a source in the `Starting` state.
+    pub fn register(&self, name: &str, kind: &'static str, enabled: bool) {
+        let mut sources = self.lock();
+        if !sources.iter().any(|(n, _)| n == name) {
+            sources.push((name.to_string(), SourceHealth::new(kind, enabled)));
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.lock()
+            .iter()
+            .any(|(n, health)| n == name && health.enabled)
+    }
+
+    /// Enables or disables a source; its task picks up the change.
+    pub fn set_enabled(&self, name: &str, enabled: bool) {
+        self.update(name, |health| health.enabled = enabled);
+        self.toggled.notify_waiters();
+    }
+
+    /// Waits until the source is enabled (or disabled, if `enabled` is false).
+    pub async fn wait_until_enabled(&self, name: &str, enabled: bool) {
+        loop {
+            // Created before the check so that a toggle in between is not missed
+            let toggled = self.toggled.notified();
+            if self.is_enabled(name) == enabled {
+                return;
+            }
+            toggled.await;
+        }
+    }
+
+    /// Records a successful request or connection.
+    pub fn success(&self, name: &str) {
+        self.update(name, |health| {
+            health.state = SourceState::Connected;
+            health.consecutive_failures = 0;
+            health.failing_since = None;
+            health.last_success = Some(self.clock.now());
+        });
+    }
+
+    /// Records a failed request or connection.
+    pub fn failure(&self, name: &str, error: impl ToString) {
+        self.update(name, |health| {
+            health.consecutive_failures += 1;
+            health.error_count += 1;
+            health.failing_since.get_or_insert_with(|| self.clock.now());
+            health.last_error = Some(error.to_string());
+            health.state = if health.consecutive_failures >= FAILING_THRESHOLD {
+                SourceState::Failing
+            } else {
+                SourceState::Degraded
+            };
+        });
+    }
+
+    /// Counts a message of the source dropped by the message queue and
+    /// returns the total so far.
+    pub fn dropped(&self, name: &str) -> u64 {
+        let mut total = 0;
+        self.update(name, |health| {
+            health.dropped += 1;
+            total = health.dropped;
+        });
+        total
+    }
+
+    /// Records a message received from a source.
+    pub fn message(&self, name: &str, text: &str) {
+        self.update(name, |health| {
+            health.last_message = Some(self.clock.now());
+            health.last_value = Some(text.to_string());
+        });
+    }
+
+    /// Returns the health of all sources in registration order.
+    pub fn snapshot(&self) -> Vec<(String, SourceHealth)> {
+        self.lock().clone()
+    }
+
+    /// Returns the health of all sources as a JSON object keyed by name.
+    pub fn to_json(&self) -> Value {
+        let sources: Map<String, Value> = self
+            .lock()
+            .iter()
+            .map(|(name, health)| (name.clone(), health.to_json()))
+            .collect();
+        Value::Object(sources)
+    }
+
+    /// Applies `f` to a registered source; unknown names are ignored.
+    fn update(&self, name: &str, f: impl FnOnce(&mut SourceHealth)) {
+        if let Some((_, health)) = self.lock().iter_mut().find(|(n, _)| n == name) {
+            f(health);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(String, SourceHealth)>> {
+        // A panic while holding the lock cannot leave the map inconsistent
+        self.sources.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
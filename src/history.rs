@@ -0,0 +1,81 @@
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+
+/// Maximum number of entries kept in the history.
+const HISTORY_LIMIT: usize = 100;
+
+/// What happened to a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HistoryKind {
+    /// Shown as an interrupt.
+    Shown,
+    /// Added to the ticker rotation.
+    Ticker,
+    /// Held back while Do Not Disturb was active.
+    Queued,
+    /// Summary of messages suppressed by a source's rate limit.
+    Suppressed {
+        duplicates: usize,
+        rate_limited: usize,
+        coalesced: usize,
+    },
+}
+
+/// A single history entry.
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub time: DateTime<Local>,
+    pub source: String,
+    pub text: String,
+    pub kind: HistoryKind,
+}
+
+/// Recent messages, newest last.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl History {
+    /// Appends an entry received at `time`, discarding the oldest one when full.
+    pub fn push(&mut self, time: DateTime<Local>, source: &str, text: &str, kind: HistoryKind) {
+        if self.entries.len() >= HISTORY_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            time,
+            source: source.to_string(),
+            text: text.to_string(),
+            kind,
+        });
+    }
+
+    /// Formats the history as a single ticker line, newest first.
+    pub fn to_ticker_text(&self) -> String {
+        if self.entries.is_empty() {
+            return "No messages in history.".to_string();
+        }
+
+        self.entries
+            .iter()
+            .rev()
+            .map(|entry| {
+                let time = entry.time.format("%H:%M");
+                match &entry.kind {
+                    HistoryKind::Shown => format!("{} {}", time, entry.text),
+                    HistoryKind::Queued => format!("{} (DND) {}", time, entry.text),
+                    HistoryKind::Ticker => format!("{} (Ticker) {}", time, entry.text),
+                    HistoryKind::Suppressed {
+                        duplicates,
+                        rate_limited,
+                        coalesced,
+                    } => format!(
+                        "{} [{}] suppressed: {} duplicate(s), {} rate-limited, {} coalesced",
+                        time, entry.source, duplicates, rate_limited, coalesced
+                    ),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("    ***    ")
+    }
+}
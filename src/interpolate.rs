@@ -0,0 +1,239 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs;
+use toml::Value;
+
+use crate::config::expand_path;
+
+/// Prefix of strings that are replaced by the content of a file.
+const FILE_PREFIX: &str = "file:";
+
+/// Looks up an environment variable.
+type Env<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Resolves references in every string of a parsed config, once when it is
+/// loaded.
+///
+/// * `${VAR}` is replaced by the environment variable `VAR`.
+/// * `${VAR:-default}` uses `default` if `VAR` is unset or empty.
+/// * `$$` produces a literal `$`.
+/// * A string of the form `file:PATH` is replaced by the trimmed content of
+///   the file (`~` is expanded).
+///
+/// Rule rewrites are left as they are, since they refer to capture groups
+/// as `$1` and `${name}`. Secrets written as `{ env = ... }` or
+/// `{ file = ... }` are not resolved here but on every request (see
+/// `config::Secret`).
+pub fn resolve(value: &mut Value) -> Result<()> {
+    resolve_with(value, &|name| std::env::var(name).ok())
+}
+
+fn resolve_with(value: &mut Value, env: Env) -> Result<()> {
+    resolve_at(value, &mut String::new(), env)
+}
+
+fn resolve_at(value: &mut Value, key_path: &mut String, env: Env) -> Result<()> {
+    match value {
+        Value::String(s) if !is_verbatim(key_path) => {
+            *s = resolve_string(s, env).with_context(|| format!("in config key '{}'", key_path))?;
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                let len = key_path.len();
+                key_path.push_str(&format!("[{}]", i));
+                resolve_at(item, key_path, env)?;
+                key_path.truncate(len);
+            }
+        }
+        Value::Table(table) => {
+            for (key, item) in table.iter_mut() {
+                let len = key_path.len();
+                if !key_path.is_empty() {
+                    key_path.push('.');
+                }
+                key_path.push_str(key);
+                resolve_at(item, key_path, env)?;
+                key_path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Returns true for `rules[N].rewrite`, whose `$` refer to capture groups.
+fn is_verbatim(key_path: &str) -> bool {
+    key_path
+        .strip_prefix("rules[")
+        .and_then(|rest| rest.split_once("]."))
+        .is_some_and(|(index, key)| index.parse::<usize>().is_ok() && key == "rewrite")
+}
+
+fn resolve_string(s: &str, env: Env) -> Result<String> {
+    let s = expand_vars(s, env)?;
+    match s.strip_prefix(FILE_PREFIX) {
+        Some(path) => {
+            let path = expand_path(path.trim());
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {:?}", path))?;
+            Ok(content.trim().to_string())
+        }
+        None => Ok(s),
+    }
+}
+
+fn expand_vars(s: &str, env: Env) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow!("unclosed '${{' in '{}'", s))?;
+            out.push_str(&lookup_var(&after[..end], env)?);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Resolves `VAR` or `VAR:-default`.
+fn lookup_var(expr: &str, env: Env) -> Result<String> {
+    let (name, default) = match expr.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (expr, None),
+    };
+
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("invalid variable name '{}' in '${{{}}}'", name, expr);
+    }
+
+    match (env(name), default) {
+        (Some(value), Some(default)) if value.is_empty() => Ok(default.to_string()),
+        (Some(value), _) => Ok(value),
+        (None, Some(default)) => Ok(default.to_string()),
+        (None, None) => bail!("environment variable {} is not set", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    fn resolved(toml: &str) -> Result<Value> {
+        let mut value = Value::Table(toml::from_str(toml).unwrap());
+        resolve_with(&mut value, &env)?;
+        Ok(value)
+    }
+
+    fn error(toml: &str) -> String {
+        format!("{:#}", resolved(toml).unwrap_err())
+    }
+
+    #[test]
+    fn variables_are_expanded() {
+        let value = resolved(
+            r#"
+            url = "https://${HOST}/api?a=${EMPTY}"
+            fallback = "${MISSING:-none} ${EMPTY:-empty} ${HOST:-other}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(value["url"].as_str(), Some("https://example.com/api?a="));
+        assert_eq!(value["fallback"].as_str(), Some("none empty example.com"));
+    }
+
+    #[test]
+    fn dollars_are_escaped_by_doubling() {
+        let value = resolved(r#"price = "$$5 for $$${HOST}, $ alone""#).unwrap();
+        assert_eq!(value["price"].as_str(), Some("$5 for $example.com, $ alone"));
+    }
+
+    #[test]
+    fn unresolvable_references_name_the_key() {
+        let e = error("[[api_sources]]\nurl = \"${MISSING}\"");
+        assert!(e.contains("api_sources[0].url"), "{}", e);
+        assert!(e.contains("environment variable MISSING is not set"), "{}", e);
+
+        let e = error(r#"a = "${1ST}""#);
+        assert!(e.contains("invalid variable name '1ST'"), "{}", e);
+        let e = error(r#"a = "${}""#);
+        assert!(e.contains("invalid variable name"), "{}", e);
+        let e = error(r#"a = "${HOST""#);
+        assert!(e.contains("unclosed"), "{}", e);
+    }
+
+    #[test]
+    fn file_references_are_replaced_by_the_content() {
+        let path = std::env::temp_dir().join(format!("infotube-secret-{}", std::process::id()));
+        fs::write(&path, "  s3cret\n").unwrap();
+        let value = resolved(&format!(
+            r#"
+            key = "file:{}"
+            literal = "see file:{}"
+            "#,
+            path.display(),
+            path.display()
+        ));
+        fs::remove_file(&path).unwrap();
+
+        let value = value.unwrap();
+        assert_eq!(value["key"].as_str(), Some("s3cret"));
+        // Only whole strings refer to files
+        assert!(value["literal"].as_str().unwrap().starts_with("see file:"));
+
+        let e = error(r#"key = "file:/nonexistent/infotube-secret""#);
+        assert!(e.contains("failed to read"), "{}", e);
+    }
+
+    #[test]
+    fn nested_tables_and_arrays_are_resolved() {
+        let value = resolved(
+            r#"
+            [[api_sources]]
+            headers = { Host = "${HOST}" }
+            query = { tags = ["${HOST}", "b"] }
+            "#,
+        )
+        .unwrap();
+        let source = &value["api_sources"][0];
+        assert_eq!(source["headers"]["Host"].as_str(), Some("example.com"));
+        assert_eq!(source["query"]["tags"][0].as_str(), Some("example.com"));
+    }
+
+    #[test]
+    fn rule_rewrites_keep_capture_groups() {
+        let value = resolved(
+            r#"
+            [[rules]]
+            pattern = "(?P<name>\\w+) is down"
+            rewrite = "${name} ($1) down"
+            sound = "${HOST}"
+            "#,
+        )
+        .unwrap();
+        let rule = &value["rules"][0];
+        assert_eq!(rule["rewrite"].as_str(), Some("${name} ($1) down"));
+        assert_eq!(rule["sound"].as_str(), Some("example.com"));
+    }
+}
@@ -0,0 +1,216 @@
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
+
+use crate::json::{self, Path};
+
+/// Remembers which array elements of a source have already been emitted.
+///
+/// A tracker either keeps the ids of the latest payload only ([`new`]),
+/// for feeds that repeat their full list in every payload, or a bounded
+/// number of recently seen ids across payloads ([`recent`]), for streams
+/// that push one or a few elements at a time.
+///
+/// [`new`]: ItemTracker::new
+/// [`recent`]: ItemTracker::recent
+#[derive(Debug)]
+pub struct ItemTracker {
+    /// Ids that are not new.
+    seen: HashSet<String>,
+    /// With a limit, the ids of `seen` in the order they were first seen.
+    order: VecDeque<String>,
+    /// `None` keeps the ids of the latest payload only; `Some(n)` keeps the
+    /// `n` ids seen most recently.
+    limit: Option<usize>,
+    /// If true, the next payload only records ids without emitting anything.
+    seed: bool,
+}
+
+impl ItemTracker {
+    /// Creates a tracker that only remembers the latest payload: an element
+    /// is new if it was not in the previous payload, so elements that stay in
+    /// a feed are never emitted twice however long the feed is.
+    ///
+    /// With `seed`, elements present in the first payload are treated as
+    /// already seen. API sources seed, since a response is a snapshot of
+    /// items that existed before the app started.
+    pub fn new(seed: bool) -> Self {
+        Self {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            limit: None,
+            seed,
+        }
+    }
+
+    /// Creates a tracker that remembers the last `limit` distinct ids across
+    /// payloads, so an element sent again in a later payload is not emitted
+    /// twice. Used by WebSocket sources, whose frames are pushed when their
+    /// items are new and usually carry only a few of them.
+    pub fn recent(limit: usize) -> Self {
+        Self {
+            seen: HashSet::new(),
+            order: VecDeque::new(),
+            limit: Some(limit.max(1)),
+            seed: false,
+        }
+    }
+
+    /// Returns the elements selected by `items_path` that were not seen
+    /// before, in document order. A path selecting an array yields its
+    /// elements; any other selected value is an element itself.
+    ///
+    /// Elements are identified by the value at `id_field`, or by their whole
+    /// content if no id field is set (or the element has no such field).
+    /// A payload without any elements (e.g. an error response) keeps the
+    /// previous ids.
+    pub fn new_items(&mut self, json: &Value, items_path: &Path, id_field: Option<&Path>) -> Vec<Value> {
+        let mut elements = Vec::new();
+        for selected in items_path.select(json) {
+            match selected.into_owned() {
+                Value::Array(items) => elements.extend(items),
+                other => elements.push(other),
+            }
+        }
+        if elements.is_empty() {
+            return elements;
+        }
+
+        let seed = std::mem::take(&mut self.seed);
+        let mut current = HashSet::new();
+        let mut new_items = Vec::new();
+        for element in elements {
+            let id = element_id(&element, id_field);
+            // Repeated ids within one payload are emitted once
+            if !current.insert(id.clone()) || self.seen.contains(&id) {
+                continue;
+            }
+            if let Some(limit) = self.limit {
+                self.remember(id, limit);
+            }
+            if !seed {
+                new_items.push(element);
+            }
+        }
+        if self.limit.is_none() {
+            self.seen = current;
+        }
+        new_items
+    }
+
+    /// Adds an id, forgetting the oldest ones beyond `limit`.
+    fn remember(&mut self, id: String, limit: usize) {
+        self.seen.insert(id.clone());
+        self.order.push_back(id);
+        while self.order.len() > limit {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn element_id(element: &Value, id_field: Option<&Path>) -> String {
+    id_field
+        .and_then(|path| path.select(element).first().and_then(|v| json::value_to_string(v)))
+        .unwrap_or_else(|| element.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(s: &str) -> Path {
+        Path::parse(s).unwrap()
+    }
+
+    fn new_ids(tracker: &mut ItemTracker, json: Value) -> Vec<i64> {
+        tracker
+            .new_items(&json, &path("$.items"), Some(&path("id")))
+            .iter()
+            .map(|item| item["id"].as_i64().unwrap())
+            .collect()
+    }
+
+    fn feed(ids: &[i64]) -> Value {
+        json!({ "items": ids.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>() })
+    }
+
+    #[test]
+    fn only_elements_missing_from_the_previous_payload_are_new() {
+        let mut tracker = ItemTracker::new(false);
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 2])), [1, 2]);
+        assert_eq!(new_ids(&mut tracker, feed(&[3, 1, 2])), [3]);
+        assert_eq!(new_ids(&mut tracker, feed(&[3, 1, 2])), Vec::<i64>::new());
+        assert_eq!(new_ids(&mut tracker, feed(&[5, 4, 3])), [5, 4]);
+    }
+
+    #[test]
+    fn seeding_skips_the_first_payload() {
+        let mut tracker = ItemTracker::new(true);
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 2])), Vec::<i64>::new());
+        assert_eq!(new_ids(&mut tracker, feed(&[3, 2, 1])), [3]);
+    }
+
+    #[test]
+    fn long_feeds_are_not_emitted_again() {
+        let mut tracker = ItemTracker::new(true);
+        let mut ids: Vec<i64> = (0..5000).collect();
+        new_ids(&mut tracker, feed(&ids));
+        ids.push(5000);
+        assert_eq!(new_ids(&mut tracker, feed(&ids)), [5000]);
+        assert_eq!(new_ids(&mut tracker, feed(&ids)), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn empty_payloads_keep_the_previous_ids() {
+        let mut tracker = ItemTracker::new(false);
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 2])), [1, 2]);
+        assert_eq!(new_ids(&mut tracker, json!({ "error": "busy" })), Vec::<i64>::new());
+        assert_eq!(new_ids(&mut tracker, feed(&[])), Vec::<i64>::new());
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 2, 3])), [3]);
+    }
+
+    #[test]
+    fn repeated_ids_in_one_payload_are_emitted_once() {
+        let mut tracker = ItemTracker::new(false);
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 1, 2])), [1, 2]);
+    }
+
+    #[test]
+    fn elements_without_an_id_are_identified_by_their_content() {
+        let mut tracker = ItemTracker::new(false);
+        let mut items = |json: Value| tracker.new_items(&json, &path("$.items"), Some(&path("id")));
+        assert_eq!(items(json!({ "items": [{ "id": 1, "v": "a" }, { "v": "b" }] })).len(), 2);
+        // Same id with other content, and the same content without an id
+        assert_eq!(items(json!({ "items": [{ "id": 1, "v": "c" }, { "v": "b" }] })).len(), 0);
+        assert_eq!(items(json!({ "items": [{ "v": "d" }] })), [json!({ "v": "d" })]);
+    }
+
+    #[test]
+    fn recent_trackers_remember_elements_across_payloads() {
+        let mut tracker = ItemTracker::recent(10);
+        assert_eq!(new_ids(&mut tracker, feed(&[1])), [1]);
+        assert_eq!(new_ids(&mut tracker, feed(&[2])), [2]);
+        assert_eq!(new_ids(&mut tracker, feed(&[1])), Vec::<i64>::new());
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 3, 3])), [3]);
+    }
+
+    #[test]
+    fn recent_trackers_forget_the_oldest_ids() {
+        let mut tracker = ItemTracker::recent(2);
+        assert_eq!(new_ids(&mut tracker, feed(&[1, 2, 3])), [1, 2, 3]);
+        assert_eq!(new_ids(&mut tracker, feed(&[3, 2, 1])), [1]);
+        // 1 was remembered again, pushing out 2
+        assert_eq!(new_ids(&mut tracker, feed(&[2])), [2]);
+    }
+
+    #[test]
+    fn non_array_selections_are_elements() {
+        let mut tracker = ItemTracker::new(false);
+        let json = json!({ "quakes": [{ "latest": { "id": 7 } }, { "latest": { "id": 8 } }] });
+        let items = tracker.new_items(&json, &path("$.quakes[*].latest"), None);
+        assert_eq!(items, [json!({ "id": 7 }), json!({ "id": 8 })]);
+        assert!(tracker.new_items(&json, &path("$.quakes[*].latest"), None).is_empty());
+    }
+}
@@ -1,14 +1,18 @@
+use serde::Deserialize;
 use serde_json::Value;
+use std::borrow::Cow;
+use std::cmp::Ordering;
 
-/// Extracts values from JSON based on a list of paths (e.g. "key/subkey").
+/// Extracts values from JSON based on a list of paths (see [`Path`]).
 /// Returns found values joined by space.
-pub fn extract_message(value: &Value, paths: &[String]) -> Option<String> {
+pub fn extract_message(value: &Value, paths: &[Path]) -> Option<String> {
     let mut results = Vec::new();
-    
+
     for path in paths {
-        let keys: Vec<&str> = path.split('/').collect();
-        if let Some(val) = extract_single_value(value, &keys) {
-            results.push(val);
+        for val in path.select(value) {
+            if let Some(text) = value_to_string(&val) {
+                results.push(text);
+            }
         }
     }
 
@@ -19,27 +23,804 @@ pub fn extract_message(value: &Value, paths: &[String]) -> Option<String> {
     }
 }
 
-fn extract_single_value(value: &Value, keys: &[&str]) -> Option<String> {
-    let mut current = value;
-    for key in keys {
-        if let Some(v) = current.get(*key) {
-            current = v;
-        } else if let Ok(idx) = key.parse::<usize>() {
-            if let Some(v) = current.get(idx) {
-                current = v;
+/// Converts a JSON value to display text. `null` has no text.
+pub(crate) fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Object(_) | Value::Array(_) => Some(value.to_string()),
+        Value::Null => None,
+    }
+}
+
+/// Compares numerically when both sides are numbers (or numeric strings),
+/// otherwise compares their text.
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (as_number(a), as_number(b)) {
+        return a.partial_cmp(&b);
+    }
+    if a == b {
+        return Some(Ordering::Equal);
+    }
+    let a = value_to_string(a)?;
+    let b = value_to_string(b)?;
+    Some(a.cmp(&b))
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// Legacy `/`-separated segment: an object key, or else an array index.
+    Legacy(String),
+    /// `.key` or `['key']`
+    Key(String),
+    /// `['a','b']`
+    Keys(Vec<String>),
+    /// `[n]`; negative indexes count from the end.
+    Index(i64),
+    /// `[start:end]`
+    Slice(Option<i64>, Option<i64>),
+    /// `.*` or `[*]`
+    Wildcard,
+    /// `[?(...)]`
+    Filter(Expr),
+    /// `..` followed by a selector, applied at every depth.
+    Descend(Box<Segment>),
+    /// `.length()`; only allowed at the end of a path.
+    Length,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// `@` followed by a relative path.
+    Current(Vec<Segment>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    /// A lone operand is true if it selects anything.
+    Exists(Operand),
+    Compare(Operand, CmpOp, Operand),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+/// A parsed query path.
+///
+/// Two syntaxes are accepted:
+///
+/// * Legacy paths such as `data/items/0/name`: each segment is an object key,
+///   or an array index if no such key exists.
+/// * JSONPath starting with `$` (or jq-style with `.`), supporting
+///   `.key`, `['key']`, `['a','b']`, `[0]`, `[-1]`, `[1:3]`, `*`,
+///   recursive descent `..key`, filters such as `[?(@.type == 'quake' && @.mag >= 5)]`
+///   and a trailing `.length()`.
+///
+/// Paths in the config are parsed when it is loaded.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Path {
+    segments: Vec<Segment>,
+}
+
+impl TryFrom<String> for Path {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Path::parse(&s)
+    }
+}
+
+impl Path {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let trimmed = path.trim();
+        if trimmed.starts_with('$') || trimmed.starts_with('.') || trimmed.starts_with('[') {
+            let mut parser = Parser::new(trimmed);
+            parser.eat('$');
+            let segments = parser.segments()?;
+            if !parser.at_end() {
+                return Err(parser.error("unexpected character"));
+            }
+            Ok(Self { segments })
+        } else {
+            let segments = path.split('/').map(|s| Segment::Legacy(s.to_string())).collect();
+            Ok(Self { segments })
+        }
+    }
+
+    /// Returns every value selected by the path, in document order.
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<Cow<'a, Value>> {
+        let (length, segments) = match self.segments.split_last() {
+            Some((Segment::Length, rest)) => (true, rest),
+            _ => (false, self.segments.as_slice()),
+        };
+
+        let selected = select_all(root, segments);
+        if length {
+            selected
+                .into_iter()
+                .filter_map(length_of)
+                .map(|n| Cow::Owned(Value::from(n)))
+                .collect()
+        } else {
+            selected.into_iter().map(Cow::Borrowed).collect()
+        }
+    }
+}
+
+fn length_of(value: &Value) -> Option<usize> {
+    match value {
+        Value::Array(a) => Some(a.len()),
+        Value::Object(o) => Some(o.len()),
+        Value::String(s) => Some(s.chars().count()),
+        _ => None,
+    }
+}
+
+fn select_all<'a>(root: &'a Value, segments: &[Segment]) -> Vec<&'a Value> {
+    let mut current = vec![root];
+    for segment in segments {
+        let mut next = Vec::new();
+        for value in current {
+            apply_segment(value, segment, &mut next);
+        }
+        current = next;
+    }
+    current
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&resolved).then_some(resolved as usize)
+}
+
+fn apply_segment<'a>(value: &'a Value, segment: &Segment, out: &mut Vec<&'a Value>) {
+    match segment {
+        Segment::Legacy(key) => {
+            if let Some(v) = value.get(key.as_str()) {
+                out.push(v);
+            } else if let Ok(idx) = key.parse::<usize>()
+                && let Some(v) = value.get(idx)
+            {
+                out.push(v);
+            }
+        }
+        Segment::Key(key) => out.extend(value.get(key.as_str())),
+        Segment::Keys(keys) => {
+            out.extend(keys.iter().filter_map(|key| value.get(key.as_str())));
+        }
+        Segment::Index(index) => {
+            if let Value::Array(items) = value
+                && let Some(i) = resolve_index(*index, items.len())
+            {
+                out.push(&items[i]);
+            }
+        }
+        Segment::Slice(start, end) => {
+            if let Value::Array(items) = value {
+                let len = items.len() as i64;
+                let clamp = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) };
+                let start = start.map_or(0, clamp);
+                let end = end.map_or(len, clamp);
+                if start < end {
+                    out.extend(&items[start as usize..end as usize]);
+                }
+            }
+        }
+        Segment::Wildcard => match value {
+            Value::Array(items) => out.extend(items),
+            Value::Object(map) => out.extend(map.values()),
+            _ => {}
+        },
+        Segment::Filter(expr) => {
+            let children: Vec<&Value> = match value {
+                Value::Array(items) => items.iter().collect(),
+                Value::Object(map) => map.values().collect(),
+                _ => Vec::new(),
+            };
+            out.extend(children.into_iter().filter(|child| expr.eval(child)));
+        }
+        Segment::Descend(inner) => {
+            apply_segment(value, inner, out);
+            let children: Vec<&Value> = match value {
+                Value::Array(items) => items.iter().collect(),
+                Value::Object(map) => map.values().collect(),
+                _ => Vec::new(),
+            };
+            for child in children {
+                apply_segment(child, segment, out);
+            }
+        }
+        // Only valid as the last segment, handled by `Path::select`
+        Segment::Length => {}
+    }
+}
+
+impl Operand {
+    fn select<'a>(&'a self, current: &'a Value) -> Option<Cow<'a, Value>> {
+        match self {
+            Operand::Current(segments) => match segments.split_last() {
+                Some((Segment::Length, rest)) => select_all(current, rest)
+                    .into_iter()
+                    .find_map(length_of)
+                    .map(|n| Cow::Owned(Value::from(n))),
+                _ => select_all(current, segments).into_iter().next().map(Cow::Borrowed),
+            },
+            Operand::Literal(value) => Some(Cow::Borrowed(value)),
+        }
+    }
+}
+
+impl Expr {
+    fn eval(&self, current: &Value) -> bool {
+        match self {
+            Expr::Exists(operand) => operand.select(current).is_some(),
+            Expr::Compare(left, op, right) => {
+                let (Some(left), Some(right)) = (left.select(current), right.select(current)) else {
+                    return false;
+                };
+                // Only numbers and strings have an order
+                let ordered = |v: &Value| v.is_number() || v.is_string();
+                // Numbers are equal by value, so that `5` matches `5.0`
+                let numeric = match (left.as_f64(), right.as_f64()) {
+                    (Some(a), Some(b)) => Some(a == b),
+                    _ => None,
+                };
+                match op {
+                    CmpOp::Eq => numeric.unwrap_or(*left == *right),
+                    CmpOp::Ne => !numeric.unwrap_or(*left == *right),
+                    _ if !ordered(&left) || !ordered(&right) => false,
+                    _ => {
+                        let ordering = match (left.as_f64(), right.as_f64()) {
+                            (Some(a), Some(b)) => a.partial_cmp(&b),
+                            _ => match (left.as_str(), right.as_str()) {
+                                (Some(a), Some(b)) => Some(a.cmp(b)),
+                                _ => None,
+                            },
+                        };
+                        match (op, ordering) {
+                            (CmpOp::Lt, Some(o)) => o == Ordering::Less,
+                            (CmpOp::Le, Some(o)) => o != Ordering::Greater,
+                            (CmpOp::Gt, Some(o)) => o == Ordering::Greater,
+                            (CmpOp::Ge, Some(o)) => o != Ordering::Less,
+                            _ => false,
+                        }
+                    }
+                }
+            }
+            Expr::And(a, b) => a.eval(current) && b.eval(current),
+            Expr::Or(a, b) => a.eval(current) || b.eval(current),
+            Expr::Not(e) => !e.eval(current),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(src: &str) -> Self {
+        Self {
+            chars: src.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn error(&self, msg: &str) -> String {
+        let src: String = self.chars.iter().collect();
+        format!("{} at position {} in path '{}'", msg, self.pos, src)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.starts_with(s) {
+            self.pos += s.chars().count();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Parses segments until something that cannot start a segment.
+    fn segments(&mut self) -> Result<Vec<Segment>, String> {
+        let mut segments = Vec::new();
+        loop {
+            if self.eat_str("..") {
+                let inner = if self.peek() == Some('[') {
+                    self.bracket()?
+                } else {
+                    self.dot_member()?
+                };
+                segments.push(Segment::Descend(Box::new(inner)));
+            } else if self.eat_str(".length()") {
+                segments.push(Segment::Length);
+                if !self.at_end() && !matches!(self.peek(), Some(')' | ' ' | '=' | '!' | '<' | '>' | '&' | '|')) {
+                    return Err(self.error("length() must be the last segment"));
+                }
+            } else if self.eat('.') {
+                segments.push(self.dot_member()?);
+            } else if self.peek() == Some('[') {
+                segments.push(self.bracket()?);
             } else {
-                return None;
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn dot_member(&mut self) -> Result<Segment, String> {
+        if self.eat('*') {
+            return Ok(Segment::Wildcard);
+        }
+        let name = self.name();
+        if name.is_empty() {
+            return Err(self.error("expected a key"));
+        }
+        Ok(Segment::Key(name))
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' || c == '$' {
+                name.push(c);
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    fn bracket(&mut self) -> Result<Segment, String> {
+        self.expect('[')?;
+        self.skip_whitespace();
+
+        let segment = if self.eat('*') {
+            Segment::Wildcard
+        } else if self.eat_str("?(") {
+            let expr = self.or_expr()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            Segment::Filter(expr)
+        } else if matches!(self.peek(), Some('\'' | '"')) {
+            let mut keys = vec![self.quoted()?];
+            self.skip_whitespace();
+            while self.eat(',') {
+                self.skip_whitespace();
+                keys.push(self.quoted()?);
+                self.skip_whitespace();
+            }
+            if keys.len() == 1 {
+                Segment::Key(keys.remove(0))
+            } else {
+                Segment::Keys(keys)
             }
         } else {
-            return None;
+            let start = self.integer()?;
+            self.skip_whitespace();
+            if self.eat(':') {
+                self.skip_whitespace();
+                let end = self.integer()?;
+                Segment::Slice(start, end)
+            } else {
+                Segment::Index(start.ok_or_else(|| self.error("expected an index"))?)
+            }
+        };
+
+        self.skip_whitespace();
+        self.expect(']')?;
+        Ok(segment)
+    }
+
+    fn integer(&mut self) -> Result<Option<i64>, String> {
+        let start = self.pos;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        if digits.is_empty() {
+            return Ok(None);
         }
+        digits
+            .parse()
+            .map(Some)
+            .map_err(|_| self.error("invalid number"))
     }
 
-    match current {
-        Value::String(s) => Some(s.clone()),
-        Value::Number(n) => Some(n.to_string()),
-        Value::Bool(b) => Some(b.to_string()),
-        Value::Object(_) | Value::Array(_) => Some(current.to_string()),
-        Value::Null => None,
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = self.peek().ok_or_else(|| self.error("expected a string"))?;
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('\\') => {
+                    self.pos += 1;
+                    let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    s.push(c);
+                    self.pos += 1;
+                }
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and_expr()?;
+        loop {
+            self.skip_whitespace();
+            if self.eat_str("||") {
+                expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary_expr()?;
+        loop {
+            self.skip_whitespace();
+            if self.eat_str("&&") {
+                expr = Expr::And(Box::new(expr), Box::new(self.unary_expr()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        if self.eat('!') {
+            return Ok(Expr::Not(Box::new(self.unary_expr()?)));
+        }
+        if self.eat('(') {
+            let expr = self.or_expr()?;
+            self.skip_whitespace();
+            self.expect(')')?;
+            return Ok(expr);
+        }
+
+        let left = self.operand()?;
+        self.skip_whitespace();
+        let op = if self.eat_str("==") {
+            CmpOp::Eq
+        } else if self.eat_str("!=") {
+            CmpOp::Ne
+        } else if self.eat_str("<=") {
+            CmpOp::Le
+        } else if self.eat_str(">=") {
+            CmpOp::Ge
+        } else if self.eat('<') {
+            CmpOp::Lt
+        } else if self.eat('>') {
+            CmpOp::Gt
+        } else {
+            return Ok(Expr::Exists(left));
+        };
+        self.skip_whitespace();
+        let right = self.operand()?;
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        self.skip_whitespace();
+        if self.eat('@') {
+            return Ok(Operand::Current(self.segments()?));
+        }
+        if matches!(self.peek(), Some('\'' | '"')) {
+            return Ok(Operand::Literal(Value::String(self.quoted()?)));
+        }
+        for (word, value) in [
+            ("true", Value::Bool(true)),
+            ("false", Value::Bool(false)),
+            ("null", Value::Null),
+        ] {
+            if self.eat_str(word) {
+                return Ok(Operand::Literal(value));
+            }
+        }
+
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.pos += 1;
+        }
+        let number: String = self.chars[start..self.pos].iter().collect();
+        serde_json::from_str::<serde_json::Number>(&number)
+            .map(|n| Operand::Literal(Value::Number(n)))
+            .map_err(|_| self.error("expected '@', a string, a number, true, false or null"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "code": 551,
+            "earthquake": {
+                "time": "2024/01/01 16:10:00",
+                "hypocenter": { "name": "Ishikawa", "magnitude": 7.6, "depth": 10 },
+                "maxScale": 70
+            },
+            "points": [
+                { "pref": "Ishikawa", "scale": 70, "type": "city" },
+                { "pref": "Niigata", "scale": 60, "type": "city" },
+                { "pref": "Toyama", "scale": 50, "type": "town" }
+            ],
+            "tags": ["a", "b", "c"],
+            "weird.key": "dotted",
+            "nothing": null
+        })
+    }
+
+    fn query<'a>(value: &'a Value, path: &str) -> Vec<Cow<'a, Value>> {
+        Path::parse(path).unwrap().select(value)
+    }
+
+    fn paths(paths: &[&str]) -> Vec<Path> {
+        paths.iter().map(|path| Path::parse(path).unwrap()).collect()
+    }
+
+    fn texts(value: &Value, path: &str) -> Vec<String> {
+        query(value, path)
+            .iter()
+            .map(|v| value_to_string(v).unwrap_or_else(|| "null".to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn legacy_paths_still_work() {
+        let v = sample();
+        assert_eq!(texts(&v, "earthquake/hypocenter/name"), ["Ishikawa"]);
+        assert_eq!(texts(&v, "points/1/pref"), ["Niigata"]);
+        assert_eq!(texts(&v, "code"), ["551"]);
+        assert_eq!(texts(&v, "weird.key"), ["dotted"]);
+        assert!(texts(&v, "points/9/pref").is_empty());
+        assert!(texts(&v, "missing/key").is_empty());
+    }
+
+    #[test]
+    fn legacy_numeric_key_prefers_object_key() {
+        let v = json!({ "0": "key", "list": ["index"] });
+        assert_eq!(texts(&v, "0"), ["key"]);
+        assert_eq!(texts(&v, "list/0"), ["index"]);
+    }
+
+    #[test]
+    fn legacy_index_into_root_array() {
+        let v = json!([{ "earthquake": { "magnitude": 4.5 } }]);
+        assert_eq!(texts(&v, "0/earthquake/magnitude"), ["4.5"]);
+    }
+
+    #[test]
+    fn dot_and_bracket_keys() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.earthquake.hypocenter.name"), ["Ishikawa"]);
+        assert_eq!(texts(&v, "$['earthquake'][\"maxScale\"]"), ["70"]);
+        assert_eq!(texts(&v, "$['weird.key']"), ["dotted"]);
+        assert_eq!(texts(&v, ".earthquake.hypocenter.depth"), ["10"]);
+    }
+
+    #[test]
+    fn root_selects_whole_document() {
+        let v = json!({ "a": 1 });
+        assert_eq!(query(&v, "$").len(), 1);
+        assert_eq!(*query(&v, "$")[0], v);
+    }
+
+    #[test]
+    fn indexes_and_last_element() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.tags[0]"), ["a"]);
+        assert_eq!(texts(&v, "$.tags[-1]"), ["c"]);
+        assert_eq!(texts(&v, "$.points[-2].pref"), ["Niigata"]);
+        assert!(texts(&v, "$.tags[3]").is_empty());
+        assert!(texts(&v, "$.tags[-4]").is_empty());
+    }
+
+    #[test]
+    fn slices() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.tags[1:]"), ["b", "c"]);
+        assert_eq!(texts(&v, "$.tags[:2]"), ["a", "b"]);
+        assert_eq!(texts(&v, "$.tags[-2:]"), ["b", "c"]);
+        assert_eq!(texts(&v, "$.tags[0:10]"), ["a", "b", "c"]);
+        assert!(texts(&v, "$.tags[2:1]").is_empty());
+    }
+
+    #[test]
+    fn wildcards() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.points[*].pref"), ["Ishikawa", "Niigata", "Toyama"]);
+        assert_eq!(texts(&v, "$.points.*.scale"), ["70", "60", "50"]);
+        assert_eq!(query(&v, "$.earthquake.hypocenter.*").len(), 3);
+    }
+
+    #[test]
+    fn union_of_keys() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.earthquake.hypocenter['name','depth']"), ["Ishikawa", "10"]);
+    }
+
+    #[test]
+    fn recursive_descent() {
+        let v = sample();
+        assert_eq!(texts(&v, "$..name"), ["Ishikawa"]);
+        assert_eq!(texts(&v, "$..pref"), ["Ishikawa", "Niigata", "Toyama"]);
+        assert_eq!(texts(&v, "$..points[-1].pref"), ["Toyama"]);
+        assert_eq!(texts(&v, "$..[?(@.type == 'town')].pref"), ["Toyama"]);
+    }
+
+    #[test]
+    fn filters() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.points[?(@.type == 'town')].pref"), ["Toyama"]);
+        assert_eq!(texts(&v, "$.points[?(@.scale >= 60)].pref"), ["Ishikawa", "Niigata"]);
+        assert_eq!(texts(&v, "$.points[?(@.scale < 60)].pref"), ["Toyama"]);
+        assert_eq!(texts(&v, "$.points[?(@.type != \"city\")].pref"), ["Toyama"]);
+        assert_eq!(
+            texts(&v, "$.points[?(@.type == 'city' && @.scale < 70)].pref"),
+            ["Niigata"]
+        );
+        assert_eq!(
+            texts(&v, "$.points[?(@.scale == 70 || @.pref == 'Toyama')].pref"),
+            ["Ishikawa", "Toyama"]
+        );
+        assert_eq!(texts(&v, "$.points[?(!(@.type == 'city'))].pref"), ["Toyama"]);
+    }
+
+    #[test]
+    fn filter_existence_and_literals() {
+        let v = json!({ "items": [
+            { "id": 1, "done": true },
+            { "id": 2, "done": false },
+            { "id": 3, "note": null },
+            { "id": 4 }
+        ] });
+        assert_eq!(texts(&v, "$.items[?(@.done)].id"), ["1", "2"]);
+        assert_eq!(texts(&v, "$.items[?(@.done == true)].id"), ["1"]);
+        assert_eq!(texts(&v, "$.items[?(@.note == null)].id"), ["3"]);
+        assert_eq!(texts(&v, "$.items[?(!@.done)].id"), ["3", "4"]);
+        assert_eq!(texts(&v, "$.items[?(@.id > 1.5)].id"), ["2", "3", "4"]);
+        assert_eq!(texts(&v, "$.items[?(@.id == -1)].id"), Vec::<String>::new());
+        // Numbers compare by value for every operator
+        assert_eq!(texts(&v, "$.items[?(@.id == 2.0)].id"), ["2"]);
+        assert_eq!(texts(&v, "$.items[?(@.id != 2.0)].id"), ["1", "3", "4"]);
+    }
+
+    #[test]
+    fn filter_on_current_value() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.tags[?(@ != 'b')]"), ["a", "c"]);
+    }
+
+    #[test]
+    fn ordering_ignores_mismatched_types() {
+        let v = json!([{ "v": "10" }, { "v": 5 }, { "v": true }]);
+        assert_eq!(query(&v, "$[?(@.v > 1)]").len(), 1);
+        assert_eq!(query(&v, "$[?(@.v > 'a')]").len(), 0);
+    }
+
+    #[test]
+    fn length() {
+        let v = sample();
+        assert_eq!(texts(&v, "$.points.length()"), ["3"]);
+        assert_eq!(texts(&v, "$.earthquake.hypocenter.length()"), ["3"]);
+        assert_eq!(texts(&v, "$.earthquake.hypocenter.name.length()"), ["8"]);
+        assert!(texts(&v, "$.code.length()").is_empty());
+        assert_eq!(texts(&v, "$.points[?(@.pref.length() > 6)].pref"), ["Ishikawa", "Niigata"]);
+    }
+
+    #[test]
+    fn invalid_paths_are_rejected() {
+        for path in [
+            "$.",
+            "$[",
+            "$['unterminated]",
+            "$.tags[abc]",
+            "$.points[?(@.scale >)]",
+            "$.tags.length().foo",
+            "$.tags]",
+        ] {
+            assert!(Path::parse(path).is_err(), "{} should not parse", path);
+            let error = serde_json::from_value::<Path>(json!(path)).unwrap_err();
+            assert!(error.to_string().contains(path), "{}", error);
+        }
+    }
+
+    #[test]
+    fn null_values_have_no_text() {
+        let v = sample();
+        assert_eq!(*query(&v, "nothing")[0], Value::Null);
+        assert_eq!(extract_message(&v, &paths(&["nothing"])), None);
+    }
+
+    #[test]
+    fn extract_message_joins_all_values() {
+        let v = sample();
+        let keys = paths(&["earthquake/hypocenter/name", "$.points[*].scale", "missing"]);
+        assert_eq!(extract_message(&v, &keys).as_deref(), Some("Ishikawa 70 60 50"));
+        assert_eq!(extract_message(&v, &paths(&["missing"])), None);
+    }
+
+    #[test]
+    fn compare_values_coerces_numbers() {
+        assert_eq!(compare_values(&json!("5.5"), &json!(5)), Some(Ordering::Greater));
+        assert_eq!(compare_values(&json!(3), &json!(3.0)), Some(Ordering::Equal));
+        assert_eq!(compare_values(&json!("ok"), &json!("ok")), Some(Ordering::Equal));
+        assert_eq!(compare_values(&json!("a"), &json!("b")), Some(Ordering::Less));
+        assert_eq!(compare_values(&json!(null), &json!("x")), None);
     }
 }
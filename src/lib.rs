@@ -0,0 +1,49 @@
+//! Infotube: a news ticker for the terminal.
+//!
+//! The binary is a thin wrapper around this library. Other programs can use
+//! it to embed the ticker in their own ratatui layouts ([`Ticker`], or
+//! `ui::render` for the whole app), run the [`App`] with their own
+//! [`Config`], or feed it from custom sources by implementing
+//! [`source::Source`].
+
+pub mod config;
+pub mod event;
+pub mod json;
+pub mod logging;
+pub mod recording;
+pub mod source;
+pub mod status_line;
+pub mod tui;
+pub mod ui;
+
+pub(crate) mod api;
+pub(crate) mod app;
+pub(crate) mod backoff;
+pub(crate) mod clock;
+pub(crate) mod condition;
+pub(crate) mod health;
+pub(crate) mod history;
+pub(crate) mod interpolate;
+pub(crate) mod items;
+pub(crate) mod limiter;
+pub(crate) mod queue;
+pub(crate) mod rules;
+pub(crate) mod schedule;
+pub(crate) mod server;
+pub(crate) mod shutdown;
+pub(crate) mod template;
+pub(crate) mod ticker;
+pub(crate) mod ws_client;
+
+pub use api::ApiSource;
+pub use app::App;
+pub use clock::Clock;
+pub use config::Config;
+pub use event::{Event, EventHandler, Message, Priority};
+pub use health::{HealthRegistry, SourceHealth, SourceState};
+pub use logging::LogBuffer;
+pub use queue::{MessageQueue, MessageSender, OverflowPolicy};
+pub use shutdown::Shutdown;
+pub use source::{Source, SourceContext, SourceRegistry};
+pub use ticker::{Ticker, TickerState};
+pub use ws_client::WsSource;
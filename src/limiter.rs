@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, RateLimitConfig};
+use crate::event::{Message, Priority};
+use crate::server;
+
+/// Result of checking a message against its source's limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The message may be shown.
+    Pass,
+    /// The message repeats a recent one and was dropped.
+    Duplicate,
+    /// The source exceeded its rate and the message was dropped.
+    RateLimited,
+    /// The source exceeded its rate and the message was folded into a summary.
+    Coalesced,
+}
+
+/// Counts for a source whose window has closed.
+#[derive(Clone, Debug)]
+pub struct WindowReport {
+    pub source: String,
+    pub duplicates: usize,
+    pub rate_limited: usize,
+    pub coalesced: usize,
+    /// Priority of the most recent coalesced message.
+    pub priority: Priority,
+}
+
+#[derive(Debug)]
+struct SourceLimiter {
+    config: RateLimitConfig,
+    window_start: Option<Instant>,
+    passed: usize,
+    duplicates: usize,
+    rate_limited: usize,
+    coalesced: usize,
+    priority: Priority,
+    /// Normalized text of recent messages and when they were last seen.
+    seen: HashMap<String, Instant>,
+}
+
+impl SourceLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            window_start: None,
+            passed: 0,
+            duplicates: 0,
+            rate_limited: 0,
+            coalesced: 0,
+            priority: Priority::default(),
+            seen: HashMap::new(),
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.config.window_sec)
+    }
+
+    fn check(&mut self, msg: &Message, now: Instant) -> Verdict {
+        if self.window_start.is_none() {
+            self.window_start = Some(now);
+        }
+
+        if let Some(ttl) = self.config.dedup_ttl_sec {
+            let ttl = Duration::from_secs(ttl);
+            self.seen.retain(|_, seen_at| now.duration_since(*seen_at) < ttl);
+
+            let key = normalize(&msg.text);
+            if self.seen.insert(key, now).is_some() {
+                self.duplicates += 1;
+                return Verdict::Duplicate;
+            }
+        }
+
+        if let Some(max) = self.config.max_messages
+            && self.passed >= max
+        {
+            if self.config.coalesce {
+                self.coalesced += 1;
+                self.priority = msg.priority;
+                return Verdict::Coalesced;
+            }
+            self.rate_limited += 1;
+            return Verdict::RateLimited;
+        }
+
+        self.passed += 1;
+        Verdict::Pass
+    }
+
+    /// Closes the window if it has expired and returns its counts.
+    fn flush(&mut self, source: &str, now: Instant) -> Option<WindowReport> {
+        let start = self.window_start?;
+        if now.duration_since(start) < self.window() {
+            return None;
+        }
+
+        let report = WindowReport {
+            source: source.to_string(),
+            duplicates: self.duplicates,
+            rate_limited: self.rate_limited,
+            coalesced: self.coalesced,
+            priority: self.priority,
+        };
+
+        self.window_start = None;
+        self.passed = 0;
+        self.duplicates = 0;
+        self.rate_limited = 0;
+        self.coalesced = 0;
+
+        if report.duplicates + report.rate_limited + report.coalesced > 0 {
+            Some(report)
+        } else {
+            None
+        }
+    }
+}
+
+/// Per-source rate limiting and duplicate suppression.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    sources: HashMap<String, SourceLimiter>,
+}
+
+impl RateLimiter {
+    /// Builds limiters for every source that has a `rate_limit` setting.
+    pub fn new(config: &Config) -> Self {
+        let mut sources = HashMap::new();
+
+        let limits = config
+            .api_sources
+            .iter()
+            .map(|c| (c.name.as_str(), &c.rate_limit))
+            .chain(config.ws_sources.iter().map(|c| (c.name.as_str(), &c.rate_limit)))
+            .chain(std::iter::once((server::SOURCE_NAME, &config.listener.rate_limit)));
+
+        for (name, limit) in limits {
+            if let Some(limit) = limit {
+                sources.insert(name.to_string(), SourceLimiter::new(limit.clone()));
+            }
+        }
+
+        Self { sources }
+    }
+
+    /// Checks a message against the limits of its source.
+    pub fn check(&mut self, msg: &Message, now: Instant) -> Verdict {
+        match self.sources.get_mut(&msg.source) {
+            Some(limiter) => limiter.check(msg, now),
+            None => Verdict::Pass,
+        }
+    }
+
+    /// Returns the counts of every window that has expired.
+    pub fn flush(&mut self, now: Instant) -> Vec<WindowReport> {
+        self.sources
+            .iter_mut()
+            .filter_map(|(source, limiter)| limiter.flush(source, now))
+            .collect()
+    }
+}
+
+/// Normalizes text so that near-identical messages compare equal.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    /// A limiter for the listener only.
+    fn limiter(toml: &str) -> RateLimiter {
+        let mut config = Config::default();
+        config.listener.rate_limit = Some(toml::from_str(toml).unwrap());
+        RateLimiter::new(&config)
+    }
+
+    fn msg(text: &str, priority: Priority) -> Message {
+        Message::new(server::SOURCE_NAME, text, priority)
+    }
+
+    fn check(limiter: &mut RateLimiter, text: &str, now: Instant) -> Verdict {
+        limiter.check(&msg(text, Priority::Normal), now)
+    }
+
+    #[test]
+    fn messages_over_the_limit_are_dropped_until_the_window_closes() {
+        let mut limiter = limiter("max_messages = 2");
+        let start = Instant::now();
+        assert_eq!(check(&mut limiter, "a", start), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "b", start), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "c", start), Verdict::RateLimited);
+        assert_eq!(check(&mut limiter, "d", start + WINDOW / 2), Verdict::RateLimited);
+
+        assert!(limiter.flush(start + WINDOW - Duration::from_millis(1)).is_empty());
+        let reports = limiter.flush(start + WINDOW);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].source, server::SOURCE_NAME);
+        assert_eq!((reports[0].rate_limited, reports[0].coalesced, reports[0].duplicates), (2, 0, 0));
+
+        // The next window starts with the next message
+        let later = start + WINDOW * 2;
+        assert_eq!(check(&mut limiter, "e", later), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "f", later), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "g", later), Verdict::RateLimited);
+    }
+
+    #[test]
+    fn windows_without_drops_are_not_reported() {
+        let mut limiter = limiter("max_messages = 2");
+        let start = Instant::now();
+        assert_eq!(check(&mut limiter, "a", start), Verdict::Pass);
+        assert!(limiter.flush(start + WINDOW).is_empty());
+        assert!(limiter.flush(start + WINDOW * 2).is_empty());
+    }
+
+    #[test]
+    fn coalesced_messages_report_the_latest_priority() {
+        let mut limiter = limiter("max_messages = 1\nwindow_sec = 10\ncoalesce = true");
+        let start = Instant::now();
+        assert_eq!(limiter.check(&msg("a", Priority::Low), start), Verdict::Pass);
+        assert_eq!(limiter.check(&msg("b", Priority::Critical), start), Verdict::Coalesced);
+        assert_eq!(limiter.check(&msg("c", Priority::High), start), Verdict::Coalesced);
+
+        assert!(limiter.flush(start + Duration::from_secs(9)).is_empty());
+        let reports = limiter.flush(start + Duration::from_secs(10));
+        assert_eq!(reports.len(), 1);
+        assert_eq!((reports[0].coalesced, reports[0].rate_limited), (2, 0));
+        assert_eq!(reports[0].priority, Priority::High);
+    }
+
+    #[test]
+    fn duplicates_ignore_whitespace_and_case() {
+        let mut limiter = limiter("dedup_ttl_sec = 60");
+        let now = Instant::now();
+        assert_eq!(check(&mut limiter, "Server  down", now), Verdict::Pass);
+        assert_eq!(check(&mut limiter, " server DOWN\n", now), Verdict::Duplicate);
+        assert_eq!(check(&mut limiter, "Server up", now), Verdict::Pass);
+
+        let reports = limiter.flush(now + WINDOW);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].duplicates, 1);
+    }
+
+    #[test]
+    fn duplicates_are_suppressed_until_quiet_for_the_ttl() {
+        let mut limiter = limiter("dedup_ttl_sec = 60");
+        let start = Instant::now();
+        assert_eq!(check(&mut limiter, "ping", start), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "ping", start + Duration::from_secs(59)), Verdict::Duplicate);
+        // Each duplicate restarts the TTL
+        assert_eq!(check(&mut limiter, "ping", start + Duration::from_secs(118)), Verdict::Duplicate);
+        assert_eq!(check(&mut limiter, "ping", start + Duration::from_secs(178)), Verdict::Pass);
+    }
+
+    #[test]
+    fn duplicates_do_not_count_towards_the_limit() {
+        let mut limiter = limiter("max_messages = 1\ndedup_ttl_sec = 60");
+        let now = Instant::now();
+        assert_eq!(check(&mut limiter, "a", now), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "a", now), Verdict::Duplicate);
+        assert_eq!(check(&mut limiter, "b", now), Verdict::RateLimited);
+
+        let reports = limiter.flush(now + WINDOW);
+        assert_eq!((reports[0].duplicates, reports[0].rate_limited), (1, 1));
+    }
+
+    #[test]
+    fn sources_are_limited_separately() {
+        let mut config = Config::default();
+        let limit: RateLimitConfig = toml::from_str("max_messages = 1").unwrap();
+        config.listener.rate_limit = Some(limit);
+        let mut limiter = RateLimiter::new(&config);
+        let now = Instant::now();
+
+        assert_eq!(check(&mut limiter, "a", now), Verdict::Pass);
+        assert_eq!(check(&mut limiter, "b", now), Verdict::RateLimited);
+        // Sources without a `rate_limit` are not limited
+        for text in ["a", "a", "b"] {
+            assert_eq!(limiter.check(&Message::new("Other", text, Priority::Normal), now), Verdict::Pass);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber, warn};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, fmt as tracing_fmt};
+
+/// Number of warnings kept for the log pane.
+const LOG_BUFFER_LIMIT: usize = 100;
+/// Number of daily log files kept before the oldest is deleted.
+const MAX_LOG_FILES: usize = 7;
+
+/// A warning or error shown in the log pane.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: DateTime<Local>,
+    pub level: Level,
+    pub target: String,
+    /// The message followed by its fields as `key=value`.
+    pub message: String,
+}
+
+/// Recent warnings and errors, collected for the in-app log pane.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+impl LogBuffer {
+    /// Returns the collected entries, newest first.
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.lock().iter().rev().cloned().collect()
+    }
+
+    fn push(&self, entry: LogEntry) {
+        let mut entries = self.lock();
+        if entries.len() >= LOG_BUFFER_LIMIT {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<LogEntry>> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogBuffer {
+    fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+        let metadata = event.metadata();
+        // Levels compare by verbosity, so this keeps WARN and ERROR
+        if *metadata.level() > Level::WARN {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.push(LogEntry {
+            time: Local::now(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Directory of the log files: `$XDG_STATE_HOME/infotube` (or the local data
+/// directory on platforms without a state directory).
+pub fn log_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("infotube"))
+}
+
+/// Installs the global subscriber: logs at `level` and above go to a daily
+/// rotated file, and warnings are collected for the log pane. Nothing is
+/// written to the terminal. The returned guard flushes the file on drop.
+///
+/// If the log file cannot be opened, logging continues to the log pane only
+/// and a warning saying so is shown there.
+pub fn init(level: LevelFilter) -> Result<(LogBuffer, Option<WorkerGuard>)> {
+    let buffer = LogBuffer::default();
+
+    let file = log_dir()
+        .context("no state directory for the log file")
+        .and_then(|dir| {
+            // Checked first: the appender reports some failures on stderr
+            std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {:?}", dir))?;
+            RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix("infotube")
+                .filename_suffix("log")
+                .max_log_files(MAX_LOG_FILES)
+                .build(&dir)
+                .with_context(|| format!("failed to open log file in {:?}", dir))
+        });
+    let (file_layer, guard, file_error) = match file {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(level);
+            (Some(layer), Some(guard), None)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+
+    tracing_subscriber::registry()
+        .with(file_layer)
+        .with(buffer.clone())
+        .try_init()
+        .context("failed to initialize logging")?;
+
+    if let Some(e) = file_error {
+        warn!(error = %format!("{:#}", e), "not logging to a file");
+    }
+
+    Ok((buffer, guard))
+}
@@ -1,53 +1,96 @@
-mod app;
-mod api;
-mod config;
-mod event;
-mod json;
-mod server;
-mod tui;
-mod ui;
-mod ws_client;
+mod cli;
 
 use anyhow::Result;
-use app::App;
-use config::Config;
-use event::EventHandler;
+use cli::Args;
+use infotube::recording::{self, Recorder};
+use infotube::status_line::StatusLine;
+use infotube::{App, Clock, Config, EventHandler, HealthRegistry, Shutdown, SourceRegistry, config, logging, tui};
+use std::time::Duration;
+
+/// How long background tasks get to stop after the UI has exited.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 1. Load config
-    let config = dirs::home_dir()
-        .map(|home| home.join(".config/infotube/config.toml"))
-        .filter(|path| path.exists())
-        .and_then(|path| Config::load(path).ok())
-        .unwrap_or_else(Config::default);
-
-    // 2. Init Event Handler
-    // Use scroll_speed_ms as the tick rate for animation
-    let events = EventHandler::new(config.scroll_speed_ms);
+    // 1. Parse arguments and start logging to a file (never to the terminal)
+    let args = Args::parse()?;
+    let (logs, _log_guard) = logging::init(args.log_level)?;
 
-    // 3. Start TCP Listener
-    server::start(config.listen_port, events.sender());
+    // 2. Load config (errors are reported before the terminal is taken over)
+    let config = match config::config_path().filter(|path| path.exists()) {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    // A single status line needs neither the sources nor the terminal
+    let status_line = StatusLine::new(args.width).tmux(args.tmux);
+    if args.status_line && !args.follow {
+        let mut app = App::new(config, HealthRegistry::new(), logs);
+        return status_line.print_once(&mut app);
+    }
 
-    // 4. Start API Poller
-    api::start(config.api_sources.clone(), events.sender());
+    // 3. Init Event Handler
+    // Use scroll_speed_ms as the tick rate for animation
+    let shutdown = Shutdown::new();
+    let health = HealthRegistry::new();
+    let mut clock = Clock::system();
+    let mut events = match &args.replay {
+        Some(path) => {
+            // Replayed events stand in for the sources, at their recorded time
+            let recording = recording::load(path)?;
+            clock = Clock::manual(recording.start);
+            EventHandler::replay(
+                recording,
+                args.replay_speed,
+                clock.clone(),
+                &config.queue,
+                health.clone(),
+                &shutdown,
+            )
+        }
+        None => {
+            let events = if args.status_line {
+                EventHandler::headless(config.scroll_speed_ms, &config.queue, health.clone(), &shutdown)
+            } else {
+                EventHandler::new(config.scroll_speed_ms, &config.queue, health.clone(), &shutdown)
+            };
 
-    // 5. Start WebSocket Client
-    ws_client::start(config.ws_sources.clone(), events.sender());
+            // 4. Start sources (TCP listener, API pollers, WebSocket clients)
+            SourceRegistry::from_config(&config).start(events.sender(), health.clone(), shutdown.clone());
+            events
+        }
+    };
+    if let Some(path) = &args.record {
+        events.record_to(Recorder::create(path, &clock)?);
+    }
 
-    // 6. Init Terminal
-    let mut terminal = tui::init()?;
+    let mut app = App::new(config, health, logs).with_clock(clock);
+    let res = if args.status_line {
+        // 5. Print the status line until stopped
+        status_line.run(&mut app, &mut events).await
+    } else {
+        // 5. Init Terminal
+        tui::install_panic_hook();
+        let mut terminal = tui::init()?;
 
-    // 7. Run App
-    let mut app = App::new(config);
-    let res = app.run(&mut terminal, &mut (events as EventHandler)).await;
+        // 6. Run App
+        let res = app.run(&mut terminal, &mut events).await;
 
-    // 8. Restore Terminal
-    tui::restore(&mut terminal)?;
+        // 7. Restore Terminal
+        tui::restore(&mut terminal)?;
+        res
+    };
 
     if let Err(err) = res {
+        tracing::error!(error = format!("{:#}", err), "application error");
         eprintln!("Application error: {:?}", err);
     }
 
+    // 8. Stop background tasks (closing connections); logs are flushed when
+    // the guard is dropped
+    if !shutdown.shutdown(SHUTDOWN_TIMEOUT).await {
+        tracing::warn!(timeout = ?SHUTDOWN_TIMEOUT, "background tasks did not stop in time");
+    }
+
     Ok(())
 }
@@ -0,0 +1,243 @@
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::warn;
+
+use crate::event::Message;
+use crate::health::HealthRegistry;
+
+/// Number of drops between two warnings for the same source.
+const DROP_LOG_INTERVAL: u64 = 1000;
+
+/// What to do with a message that arrives while the queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued message to make room.
+    #[default]
+    DropOldest,
+    /// Discard the new message.
+    DropNewest,
+    /// Replace the latest queued message from the same source, so a flooding
+    /// source keeps only its newest message. Falls back to dropping the oldest.
+    Coalesce,
+}
+
+#[derive(Debug)]
+struct Shared {
+    queue: Mutex<VecDeque<Message>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    /// Wakes the receiver when a message is pushed.
+    pushed: Notify,
+    /// Per-source drop counts are kept here.
+    health: HealthRegistry,
+}
+
+/// Bounded queue of external messages between the sources and the app.
+///
+/// Pushing never blocks the source; when the queue is full the overflow policy
+/// decides which message is dropped, and the drop is counted for the source
+/// that lost the message.
+#[derive(Debug, Clone)]
+pub struct MessageQueue {
+    shared: Arc<Shared>,
+}
+
+/// Handle used by sources to push messages.
+#[derive(Debug, Clone)]
+pub struct MessageSender {
+    shared: Arc<Shared>,
+}
+
+impl MessageQueue {
+    pub fn new(capacity: usize, policy: OverflowPolicy, health: HealthRegistry) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+                capacity: capacity.max(1),
+                policy,
+                pushed: Notify::new(),
+                health,
+            }),
+        }
+    }
+
+    pub fn sender(&self) -> MessageSender {
+        MessageSender {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Waits for the next message.
+    pub async fn recv(&self) -> Message {
+        loop {
+            if let Some(msg) = self.shared.lock().pop_front() {
+                return msg;
+            }
+            // A push between the check and here leaves a permit, so it is not missed
+            self.shared.pushed.notified().await;
+        }
+    }
+}
+
+impl MessageSender {
+    /// Queues a message, applying the overflow policy if the queue is full.
+    pub fn send(&self, msg: Message) {
+        let shared = &self.shared;
+        let dropped = {
+            let mut queue = shared.lock();
+            if queue.len() < shared.capacity {
+                queue.push_back(msg);
+                None
+            } else {
+                match shared.policy {
+                    OverflowPolicy::DropNewest => Some(msg),
+                    OverflowPolicy::DropOldest => {
+                        let oldest = queue.pop_front();
+                        queue.push_back(msg);
+                        oldest
+                    }
+                    OverflowPolicy::Coalesce => {
+                        match queue.iter_mut().rev().find(|queued| queued.source == msg.source) {
+                            Some(queued) => Some(std::mem::replace(queued, msg)),
+                            None => {
+                                let oldest = queue.pop_front();
+                                queue.push_back(msg);
+                                oldest
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        if let Some(dropped) = dropped {
+            // Logged on the first drop and then every DROP_LOG_INTERVAL drops
+            let count = shared.health.dropped(&dropped.source);
+            if count % DROP_LOG_INTERVAL == 1 {
+                warn!(source = %dropped.source, dropped = count, capacity = shared.capacity, "message queue full; dropping messages");
+            }
+        }
+        shared.pushed.notify_one();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Message>> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Priority;
+
+    fn queue(capacity: usize, policy: OverflowPolicy) -> (MessageQueue, HealthRegistry) {
+        let health = HealthRegistry::new();
+        health.register("a", "api", true);
+        health.register("b", "ws", true);
+        (MessageQueue::new(capacity, policy, health.clone()), health)
+    }
+
+    fn send(queue: &MessageQueue, source: &str, text: &str) {
+        queue.sender().send(Message::new(source, text, Priority::Normal));
+    }
+
+    /// Takes the queued messages as `source:text`.
+    fn drain(queue: &MessageQueue) -> Vec<String> {
+        queue
+            .shared
+            .lock()
+            .drain(..)
+            .map(|msg| format!("{}:{}", msg.source, msg.text))
+            .collect()
+    }
+
+    fn dropped(health: &HealthRegistry) -> Vec<(String, u64)> {
+        health.snapshot().into_iter().map(|(name, h)| (name, h.dropped)).collect()
+    }
+
+    fn counts(a: u64, b: u64) -> Vec<(String, u64)> {
+        vec![("a".to_string(), a), ("b".to_string(), b)]
+    }
+
+    #[test]
+    fn nothing_is_dropped_below_capacity() {
+        for policy in [OverflowPolicy::DropOldest, OverflowPolicy::DropNewest, OverflowPolicy::Coalesce] {
+            let (queue, health) = queue(3, policy);
+            send(&queue, "a", "1");
+            send(&queue, "a", "2");
+            send(&queue, "b", "3");
+            assert_eq!(drain(&queue), ["a:1", "a:2", "b:3"], "{:?}", policy);
+            assert_eq!(dropped(&health), counts(0, 0));
+        }
+    }
+
+    #[test]
+    fn drop_oldest_makes_room_for_new_messages() {
+        let (queue, health) = queue(2, OverflowPolicy::DropOldest);
+        send(&queue, "a", "1");
+        send(&queue, "b", "2");
+        send(&queue, "b", "3");
+        send(&queue, "a", "4");
+        assert_eq!(drain(&queue), ["b:3", "a:4"]);
+        // Counted for the sources that lost a message
+        assert_eq!(dropped(&health), counts(1, 1));
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_queued_messages() {
+        let (queue, health) = queue(2, OverflowPolicy::DropNewest);
+        send(&queue, "a", "1");
+        send(&queue, "a", "2");
+        send(&queue, "b", "3");
+        send(&queue, "b", "4");
+        send(&queue, "a", "5");
+        assert_eq!(drain(&queue), ["a:1", "a:2"]);
+        assert_eq!(dropped(&health), counts(1, 2));
+    }
+
+    #[test]
+    fn coalesce_replaces_the_latest_message_of_the_source() {
+        let (queue, health) = queue(3, OverflowPolicy::Coalesce);
+        send(&queue, "a", "1");
+        send(&queue, "b", "2");
+        send(&queue, "a", "3");
+        send(&queue, "a", "4");
+        send(&queue, "b", "5");
+        assert_eq!(drain(&queue), ["a:1", "b:5", "a:4"]);
+        assert_eq!(dropped(&health), counts(1, 1));
+    }
+
+    #[test]
+    fn coalesce_drops_the_oldest_for_a_new_source() {
+        let (queue, health) = queue(2, OverflowPolicy::Coalesce);
+        send(&queue, "a", "1");
+        send(&queue, "a", "2");
+        send(&queue, "b", "3");
+        assert_eq!(drain(&queue), ["a:2", "b:3"]);
+        assert_eq!(dropped(&health), counts(1, 0));
+    }
+
+    #[test]
+    fn capacity_is_at_least_one() {
+        let (queue, health) = queue(0, OverflowPolicy::DropOldest);
+        send(&queue, "a", "1");
+        send(&queue, "b", "2");
+        assert_eq!(drain(&queue), ["b:2"]);
+        assert_eq!(dropped(&health), counts(1, 0));
+    }
+
+    #[tokio::test]
+    async fn recv_waits_for_a_message() {
+        let (queue, _) = queue(2, OverflowPolicy::DropOldest);
+        let sender = queue.sender();
+        let receiver = tokio::spawn(async move { queue.recv().await.text });
+        tokio::task::yield_now().await;
+        sender.send(Message::new("a", "hello", Priority::Normal));
+        assert_eq!(receiver.await.unwrap(), "hello");
+    }
+}
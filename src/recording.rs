@@ -0,0 +1,236 @@
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{self, Instant};
+use tracing::info;
+
+use crate::clock::Clock;
+use crate::event::Event;
+
+/// First line of a recording.
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    /// Wall-clock time at the start of the recording (RFC 3339).
+    start: String,
+}
+
+/// A recorded session.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// Wall-clock time at the start of the recording.
+    pub start: DateTime<Local>,
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    /// Wall-clock time at which `event` was received.
+    pub fn time_of(&self, event: &RecordedEvent) -> DateTime<Local> {
+        self.start + Duration::from_millis(event.t_ms)
+    }
+}
+
+/// An event and when the app received it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Milliseconds since the start of the recording.
+    pub t_ms: u64,
+    pub event: Event,
+}
+
+#[derive(Serialize)]
+struct RecordedEventRef<'a> {
+    t_ms: u64,
+    event: &'a Event,
+}
+
+/// Writes the events received by the app to a file, one JSON object per
+/// line: a header with the wall-clock start time (`{"start": ...}`), then
+/// one [`RecordedEvent`] per event.
+#[derive(Debug)]
+pub struct Recorder {
+    file: LineWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Creates (or truncates) the file at `path` and writes the header with
+    /// the time of `clock`. Event times are counted from now.
+    pub fn create(path: impl AsRef<Path>, clock: &Clock) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("failed to create {:?}", path))?;
+        let mut file = LineWriter::new(file);
+        let header = Header {
+            start: clock.now().to_rfc3339(),
+        };
+        serde_json::to_writer(&mut file, &header)?;
+        file.write_all(b"\n")?;
+        Ok(Self {
+            file,
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends an event. Each line is written out immediately, so that the
+    /// recording is complete even if the app crashes.
+    pub fn record(&mut self, event: &Event) -> Result<()> {
+        let entry = RecordedEventRef {
+            t_ms: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+        serde_json::to_writer(&mut self.file, &entry)?;
+        self.file.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Reads a recording made by [`Recorder`]. Blank lines are ignored.
+pub fn load(path: impl AsRef<Path>) -> Result<Recording> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((i, line)) = lines.next() else {
+        bail!("{:?} is empty", path);
+    };
+    let header: Header = serde_json::from_str(line)
+        .with_context(|| format!("invalid header on line {} of {:?}", i + 1, path))?;
+    let start = DateTime::parse_from_rfc3339(&header.start)
+        .with_context(|| format!("invalid start time on line {} of {:?}", i + 1, path))?
+        .with_timezone(&Local);
+
+    let events = lines
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("invalid event on line {} of {:?}", i + 1, path))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Recording { start, events })
+}
+
+/// Sends the recorded events to `tx` with their original spacing, divided by
+/// `speed` (2.0 replays twice as fast).
+///
+/// Before each event, `clock` (a manual clock) is set to the wall-clock time
+/// at which it was recorded, so that schedules behave as they did.
+pub async fn replay(recording: Recording, speed: f64, clock: Clock, tx: mpsc::Sender<Event>) {
+    let start = Instant::now();
+    for recorded in &recording.events {
+        let offset = Duration::from_secs_f64(recorded.t_ms as f64 / 1000.0 / speed);
+        time::sleep_until(start + offset).await;
+        clock.set(recording.time_of(recorded));
+        if tx.send(recorded.event.clone()).await.is_err() {
+            return;
+        }
+    }
+    info!("replay finished");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{Message, Priority, Signal};
+    use chrono::TimeZone;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn start() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 1, 6, 21, 59, 59).unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recording_round_trips() {
+        let path =
+            std::env::temp_dir().join(format!("infotube-recording-{}.jsonl", std::process::id()));
+        let mut recorder = Recorder::create(&path, &Clock::manual(start())).unwrap();
+        recorder.record(&Event::Resize(80)).unwrap();
+        time::advance(Duration::from_millis(100)).await;
+        recorder.record(&Event::Tick).unwrap();
+        time::advance(Duration::from_millis(50)).await;
+        recorder
+            .record(&Event::Message(Message::new(
+                "api",
+                "[api] hi",
+                Priority::High,
+            )))
+            .unwrap();
+        recorder
+            .record(&Event::Key(KeyEvent::new(
+                KeyCode::Char('c'),
+                KeyModifiers::CONTROL,
+            )))
+            .unwrap();
+        recorder.record(&Event::Signal(Signal::Hangup)).unwrap();
+        drop(recorder);
+
+        let recording = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(recording.start, start());
+        let events = recording.events;
+        let times: Vec<u64> = events.iter().map(|e| e.t_ms).collect();
+        assert_eq!(times, [0, 100, 150, 150, 150]);
+        assert!(matches!(events[0].event, Event::Resize(80)));
+        assert!(matches!(events[1].event, Event::Tick));
+        match &events[2].event {
+            Event::Message(msg) => {
+                assert_eq!(msg.source, "api");
+                assert_eq!(msg.text, "[api] hi");
+                assert_eq!(msg.priority, Priority::High);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match &events[3].event {
+            Event::Key(key) => {
+                assert_eq!(key.code, KeyCode::Char('c'));
+                assert_eq!(key.modifiers, KeyModifiers::CONTROL);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(matches!(events[4].event, Event::Signal(Signal::Hangup)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replay_keeps_the_spacing_divided_by_speed() {
+        let recording = Recording {
+            start: start(),
+            events: vec![
+                RecordedEvent {
+                    t_ms: 0,
+                    event: Event::Tick,
+                },
+                RecordedEvent {
+                    t_ms: 1000,
+                    event: Event::Resize(40),
+                },
+            ],
+        };
+        let clock = Clock::manual(Local::now());
+        let (tx, mut rx) = mpsc::channel(4);
+        let begin = Instant::now();
+        tokio::spawn(replay(recording, 4.0, clock.clone(), tx));
+
+        // The clock shows the recorded time, not the replay time
+        assert!(matches!(rx.recv().await, Some(Event::Tick)));
+        assert_eq!(clock.now(), start());
+        assert!(matches!(rx.recv().await, Some(Event::Resize(40))));
+        assert_eq!(begin.elapsed(), Duration::from_millis(250));
+        assert_eq!(clock.now(), Local.with_ymd_and_hms(2025, 1, 6, 22, 0, 0).unwrap());
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[test]
+    fn recordings_need_a_header() {
+        let path = std::env::temp_dir().join(format!("infotube-no-header-{}.jsonl", std::process::id()));
+        fs::write(&path, "{\"t_ms\":0,\"event\":\"tick\"}\n").unwrap();
+        let error = format!("{:#}", load(&path).unwrap_err());
+        fs::remove_file(&path).unwrap();
+        assert!(error.contains("invalid header on line 1"), "{}", error);
+    }
+}
@@ -0,0 +1,278 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::event::{Message, MessageStyle, Priority, Route};
+use crate::json::{self, Path};
+
+/// Regular expression compiled when the config is loaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern(pub Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Regex::new(&s).map(Pattern)
+    }
+}
+
+/// A rule that matches messages and changes how they are handled.
+///
+/// All conditions that are set must hold for the rule to match.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    // --- Conditions ---
+    /// Exact source name
+    pub source: Option<String>,
+    /// Regular expression matched against the message text
+    pub pattern: Option<Pattern>,
+    /// JSON field paths and the values they must have (numbers compare numerically)
+    #[serde(default, deserialize_with = "json_conditions")]
+    pub json: Vec<(Path, Value)>,
+
+    // --- Actions ---
+    /// Discard the message
+    #[serde(default)]
+    pub drop: bool,
+    /// New priority
+    pub priority: Option<Priority>,
+    /// Replacement text; `$1`, `${name}` refer to groups of `pattern`
+    pub rewrite: Option<String>,
+    /// Colors used when the message is shown
+    pub style: Option<MessageStyle>,
+    /// Where the message is shown
+    pub route: Option<Route>,
+    /// Sound played for the message
+    pub sound: Option<String>,
+}
+
+impl Rule {
+    fn matches(&self, msg: &Message) -> bool {
+        if let Some(source) = &self.source
+            && *source != msg.source
+        {
+            return false;
+        }
+
+        if let Some(Pattern(re)) = &self.pattern
+            && !re.is_match(&msg.text)
+        {
+            return false;
+        }
+
+        self.json.iter().all(|(path, expected)| {
+            msg.json.as_ref().is_some_and(|json| {
+                path.select(json)
+                    .iter()
+                    .any(|actual| json::compare_values(actual, expected) == Some(Ordering::Equal))
+            })
+        })
+    }
+
+    fn apply(&self, msg: &mut Message) {
+        if let Some(priority) = self.priority {
+            msg.priority = priority;
+        }
+        if let Some(rewrite) = &self.rewrite {
+            msg.text = match &self.pattern {
+                Some(Pattern(re)) => re.replace_all(&msg.text, rewrite.as_str()).into_owned(),
+                None => rewrite.clone(),
+            };
+        }
+        if let Some(style) = &self.style {
+            msg.style = Some(style.clone());
+        }
+        if let Some(route) = self.route {
+            msg.route = route;
+        }
+        if let Some(sound) = &self.sound {
+            msg.sound = Some(sound.clone());
+        }
+    }
+}
+
+/// Parses the keys of the `json` table as paths.
+fn json_conditions<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(Path, Value)>, D::Error> {
+    HashMap::<String, Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(path, value)| Ok((Path::parse(&path).map_err(de::Error::custom)?, value)))
+        .collect()
+}
+
+/// The ordered list of rules applied to every incoming message.
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Applies every matching rule in order.
+    /// Returns `None` if a rule dropped the message.
+    pub fn apply(&self, mut msg: Message) -> Option<Message> {
+        for rule in &self.rules {
+            if !rule.matches(&msg) {
+                continue;
+            }
+            if rule.drop {
+                return None;
+            }
+            rule.apply(&mut msg);
+        }
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rules(toml: &str) -> RuleSet {
+        #[derive(Deserialize)]
+        struct Rules {
+            rules: Vec<Rule>,
+        }
+        RuleSet::new(toml::from_str::<Rules>(toml).unwrap().rules)
+    }
+
+    fn msg(source: &str, text: &str) -> Message {
+        Message::new(source, text, Priority::Normal)
+    }
+
+    #[test]
+    fn conditions_must_all_hold() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            source = "Quake"
+            pattern = "^M(\\d)"
+            json = { "$.tsunami" = "Warning", "depth" = 10 }
+            priority = "critical"
+            "#,
+        );
+        let mut quake = msg("Quake", "M6 Tokyo");
+        quake.json = Some(json!({ "tsunami": "Warning", "depth": 10.0 }));
+        assert_eq!(rules.apply(quake.clone()).unwrap().priority, Priority::Critical);
+
+        let mut other_source = quake.clone();
+        other_source.source = "Quakes".to_string();
+        let mut other_text = quake.clone();
+        other_text.text = "Tokyo M6".to_string();
+        let mut other_json = quake.clone();
+        other_json.json = Some(json!({ "tsunami": "None", "depth": 10 }));
+        let mut missing_json = quake.clone();
+        missing_json.json = None;
+        for msg in [other_source, other_text, other_json, missing_json] {
+            assert_eq!(rules.apply(msg).unwrap().priority, Priority::Normal);
+        }
+    }
+
+    #[test]
+    fn rules_apply_in_order_and_drop_stops() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            pattern = "heartbeat"
+            drop = true
+
+            [[rules]]
+            priority = "low"
+            sound = "Ping"
+
+            [[rules]]
+            source = "Status"
+            priority = "high"
+
+            [[rules]]
+            source = "Status"
+            pattern = "down"
+            drop = true
+            "#,
+        );
+        assert!(rules.apply(msg("Status", "heartbeat")).is_none());
+        assert!(rules.apply(msg("Status", "api down")).is_none());
+
+        // Later rules override earlier ones
+        let status = rules.apply(msg("Status", "api up")).unwrap();
+        assert_eq!((status.priority, status.sound.as_deref()), (Priority::High, Some("Ping")));
+        let other = rules.apply(msg("Other", "api down")).unwrap();
+        assert_eq!((other.priority, other.sound.as_deref()), (Priority::Low, Some("Ping")));
+    }
+
+    #[test]
+    fn later_rules_see_rewritten_text() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            pattern = "^ERR (.*)$"
+            rewrite = "Error: $1"
+
+            [[rules]]
+            pattern = "^Error:"
+            route = "ticker"
+            "#,
+        );
+        let msg = rules.apply(msg("Log", "ERR disk full")).unwrap();
+        assert_eq!(msg.text, "Error: disk full");
+        assert_eq!(msg.route, Route::Ticker);
+    }
+
+    #[test]
+    fn rewrites_use_capture_groups() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            pattern = "(?P<host>\\w+) is (\\w+)"
+            rewrite = "${host}: $2"
+
+            [[rules]]
+            source = "Fixed"
+            rewrite = "replaced"
+            "#,
+        );
+        assert_eq!(rules.apply(msg("Status", "[web is down, db is up]")).unwrap().text, "[web: down, db: up]");
+        // Without a pattern the whole text is replaced
+        assert_eq!(rules.apply(msg("Fixed", "anything")).unwrap().text, "replaced");
+    }
+
+    #[test]
+    fn style_and_route_override_the_defaults() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            source = "Weather"
+            route = "ticker"
+            style = { fg = "Yellow" }
+            "#,
+        );
+        let weather = rules.apply(msg("Weather", "Sunny")).unwrap();
+        assert_eq!(weather.route, Route::Ticker);
+        let style = weather.style.unwrap();
+        assert_eq!((style.fg.as_deref(), style.bg.as_deref()), (Some("Yellow"), None));
+
+        let other = rules.apply(msg("Other", "Sunny")).unwrap();
+        assert_eq!(other.route, Route::Interrupt);
+        assert!(other.style.is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_and_paths_are_rejected() {
+        assert!(toml::from_str::<Rule>("pattern = \"(unclosed\"").is_err());
+        let error = toml::from_str::<Rule>("json = { \"$.a[\" = 1 }").unwrap_err().to_string();
+        assert!(error.contains("$.a["), "{}", error);
+    }
+
+    #[test]
+    fn messages_pass_without_rules() {
+        let msg = RuleSet::default().apply(msg("Status", "up")).unwrap();
+        assert_eq!((msg.text.as_str(), msg.priority), ("up", Priority::Normal));
+    }
+}
@@ -0,0 +1,542 @@
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Weekday,
+};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// Time of day in `HH:MM` form. `24:00` is accepted as the end of the day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TimeOfDay {
+    /// Minutes since midnight (0..=1440).
+    minutes: u32,
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if s.trim() == "24:00" {
+            return Ok(Self { minutes: 24 * 60 });
+        }
+        let time = NaiveTime::parse_from_str(s.trim(), "%H:%M")
+            .map_err(|_| format!("invalid time of day '{}', expected HH:MM", s))?;
+        Ok(Self {
+            minutes: time.hour() * 60 + time.minute(),
+        })
+    }
+}
+
+/// Day selector used by time windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Days {
+    /// A single day of the week.
+    Day(Weekday),
+    /// Monday to Friday.
+    Weekdays,
+    /// Saturday and Sunday.
+    Weekends,
+}
+
+impl TryFrom<String> for Days {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "weekdays" => Ok(Days::Weekdays),
+            "weekends" => Ok(Days::Weekends),
+            _ => Weekday::from_str(&s)
+                .map(Days::Day)
+                .map_err(|_| format!("invalid day '{}'", s)),
+        }
+    }
+}
+
+impl Days {
+    fn matches(&self, day: Weekday) -> bool {
+        match self {
+            Days::Day(d) => *d == day,
+            Days::Weekdays => !matches!(day, Weekday::Sat | Weekday::Sun),
+            Days::Weekends => matches!(day, Weekday::Sat | Weekday::Sun),
+        }
+    }
+}
+
+/// A recurring daily time window, e.g. 22:00-07:00 on weekdays.
+///
+/// Windows where `end` is before `start` wrap past midnight; the day filter
+/// applies to the day the window starts on.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimeWindow {
+    /// Start of the window (defaults to 00:00)
+    #[serde(default)]
+    pub start: Option<TimeOfDay>,
+    /// End of the window (defaults to 24:00)
+    #[serde(default)]
+    pub end: Option<TimeOfDay>,
+    /// Days the window applies to (empty means every day)
+    #[serde(default)]
+    pub days: Vec<Days>,
+}
+
+impl TimeWindow {
+    /// Returns true if `now` falls inside this window.
+    pub fn contains<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> bool {
+        let start = self.start.map_or(0, |t| t.minutes);
+        let end = self.end.map_or(24 * 60, |t| t.minutes);
+        let minute = now.hour() * 60 + now.minute();
+        let today = now.weekday();
+
+        if start <= end {
+            start <= minute && minute < end && self.matches_day(today)
+        } else if minute >= start {
+            self.matches_day(today)
+        } else if minute < end {
+            let yesterday = (now.clone() - Duration::days(1)).weekday();
+            self.matches_day(yesterday)
+        } else {
+            false
+        }
+    }
+
+    fn matches_day(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.iter().any(|d| d.matches(day))
+    }
+}
+
+/// How far ahead `CronSchedule::next_after` searches before giving up.
+const CRON_SEARCH_DAYS: i64 = 366 * 5;
+
+/// A five-field cron expression: minute, hour, day of month, month, day of week.
+///
+/// Each field accepts `*`, numbers, ranges (`1-5`), steps (`*/5`, `9-18/2`)
+/// and comma-separated lists. Months and days of the week also accept
+/// three-letter names (`jan`, `mon`); Sunday is `0` or `7`. As in cron, if both
+/// the day of month and the day of week are restricted, either may match.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// True if the day of month field is `*`.
+    any_day_of_month: bool,
+    /// True if the day of week field is `*`.
+    any_day_of_week: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(format!(
+                "invalid cron expression '{}', expected 5 fields (minute hour day month weekday)",
+                s
+            ));
+        };
+
+        let field = |text: &str, min: u32, max: u32, names: &[&str], name_base: u32| {
+            parse_cron_field(text, min, max, names, name_base)
+                .map_err(|e| format!("invalid cron expression '{}': {}", s, e))
+        };
+
+        let mut days_of_week = field(day_of_week, 0, 7, &DAY_NAMES, 0)?;
+        // 7 is another name for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            minutes: field(minute, 0, 59, &[], 0)?,
+            hours: field(hour, 0, 23, &[], 0)?,
+            days_of_month: field(day_of_month, 1, 31, &[], 0)?,
+            months: field(month, 1, 12, &MONTH_NAMES, 1)?,
+            days_of_week,
+            any_day_of_month: day_of_month == "*",
+            any_day_of_week: day_of_week == "*",
+        })
+    }
+}
+
+/// Parses one cron field into a bit set of allowed values.
+fn parse_cron_field(
+    text: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    name_base: u32,
+) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let lower = s.to_ascii_lowercase();
+        let n = match names.iter().position(|name| *name == lower) {
+            Some(i) => i as u32 + name_base,
+            None => s.parse().map_err(|_| format!("invalid value '{}'", s))?,
+        };
+        if n < min || n > max {
+            return Err(format!("value {} is out of range {}-{}", n, min, max));
+        }
+        Ok(n)
+    };
+
+    let mut bits = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be at least 1".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else {
+            let start = value(range)?;
+            // `5/15` means every 15 starting at 5
+            (start, if part.contains('/') { max } else { start })
+        };
+        if start > end {
+            return Err(format!("invalid range '{}'", range));
+        }
+
+        for n in (start..=end).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    /// Returns the first matching minute strictly after `now`, or `None` if
+    /// the expression never matches (e.g. `0 0 31 2 *`).
+    ///
+    /// Times skipped by a DST change are not run that day; times repeated
+    /// by one run at their first occurrence.
+    pub fn next_after<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = now.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(CRON_SEARCH_DAYS);
+        let mut t = start;
+
+        while t < limit {
+            if !has(self.months, t.month()) {
+                // Jump to the first day of the next month
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN);
+            } else if !self.matches_day(&t) {
+                t = (t.date() + Duration::days(1)).and_time(NaiveTime::MIN);
+            } else if !has(self.hours, t.hour()) {
+                t = start_of_hour(t) + Duration::hours(1);
+            } else if !has(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                // Times skipped by a DST change do not exist locally
+                match now.timezone().from_local_datetime(&t).earliest() {
+                    Some(time) => return Some(time),
+                    None => t += Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, t: &NaiveDateTime) -> bool {
+        let day_of_month = has(self.days_of_month, t.day());
+        let day_of_week = has(self.days_of_week, t.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+fn has(bits: u64, n: u32) -> bool {
+    bits & (1 << n) != 0
+}
+
+fn start_of_hour(t: NaiveDateTime) -> NaiveDateTime {
+    t.date().and_hms_opt(t.hour(), 0, 0).unwrap_or(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, LocalResult, Utc};
+
+    fn cron(expr: &str) -> CronSchedule {
+        CronSchedule::try_from(expr.to_string()).unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap().and_utc()
+    }
+
+    /// The next `n` times `expr` runs after `start`.
+    fn runs(expr: &str, start: &str, n: usize) -> Vec<String> {
+        let schedule = cron(expr);
+        let mut now = utc(start);
+        let mut runs = Vec::new();
+        for _ in 0..n {
+            now = schedule.next_after(&now).unwrap();
+            runs.push(now.format("%a %Y-%m-%d %H:%M").to_string());
+        }
+        runs
+    }
+
+    #[test]
+    fn cron_fields_accept_ranges_steps_and_lists() {
+        // 2025-01-06 is a Monday
+        assert_eq!(
+            runs("*/20 9-10 * * *", "2025-01-06 08:59", 4),
+            [
+                "Mon 2025-01-06 09:00",
+                "Mon 2025-01-06 09:20",
+                "Mon 2025-01-06 09:40",
+                "Mon 2025-01-06 10:00"
+            ]
+        );
+        assert_eq!(
+            runs("5/25 12,18 * * *", "2025-01-06 12:30", 4),
+            [
+                "Mon 2025-01-06 12:55",
+                "Mon 2025-01-06 18:05",
+                "Mon 2025-01-06 18:30",
+                "Mon 2025-01-06 18:55"
+            ]
+        );
+        assert_eq!(
+            runs("0 9-17/4 1 * *", "2025-01-06 00:00", 3),
+            ["Sat 2025-02-01 09:00", "Sat 2025-02-01 13:00", "Sat 2025-02-01 17:00"]
+        );
+    }
+
+    #[test]
+    fn cron_accepts_month_and_day_names() {
+        assert_eq!(
+            runs("30 8 * feb-mar MON,fri", "2025-01-06 00:00", 3),
+            ["Mon 2025-02-03 08:30", "Fri 2025-02-07 08:30", "Mon 2025-02-10 08:30"]
+        );
+        // Sunday is 0 or 7
+        for expr in ["0 0 * * 0", "0 0 * * 7", "0 0 * * sun", "0 0 * * 6-7"] {
+            assert_eq!(runs(expr, "2025-01-11 12:00", 1), ["Sun 2025-01-12 00:00"], "{}", expr);
+        }
+    }
+
+    #[test]
+    fn next_after_is_strictly_later() {
+        assert_eq!(runs("0 * * * *", "2025-01-06 09:00", 1), ["Mon 2025-01-06 10:00"]);
+        let schedule = cron("* * * * *");
+        let now = utc("2025-12-31 23:59") + Duration::seconds(30);
+        assert_eq!(schedule.next_after(&now), Some(utc("2026-01-01 00:00")));
+    }
+
+    #[test]
+    fn restricted_day_of_month_and_week_either_match() {
+        // The 13th, and every Friday
+        assert_eq!(
+            runs("0 12 13 * fri", "2025-06-01 00:00", 4),
+            [
+                "Fri 2025-06-06 12:00",
+                "Fri 2025-06-13 12:00",
+                "Fri 2025-06-20 12:00",
+                "Fri 2025-06-27 12:00"
+            ]
+        );
+        assert_eq!(
+            runs("0 12 1 * fri", "2025-06-01 00:00", 3),
+            ["Sun 2025-06-01 12:00", "Fri 2025-06-06 12:00", "Fri 2025-06-13 12:00"]
+        );
+        // With `*` in one of them only the other counts
+        assert_eq!(runs("0 12 13 * *", "2025-06-01 00:00", 2), ["Fri 2025-06-13 12:00", "Sun 2025-07-13 12:00"]);
+        assert_eq!(runs("0 12 * * fri", "2025-06-01 00:00", 2), ["Fri 2025-06-06 12:00", "Fri 2025-06-13 12:00"]);
+    }
+
+    #[test]
+    fn impossible_dates_never_run() {
+        assert_eq!(cron("0 0 31 2 *").next_after(&utc("2025-01-01 00:00")), None);
+        assert_eq!(cron("0 0 30 feb *").next_after(&utc("2025-01-01 00:00")), None);
+        // Leap days are found within the search range
+        assert_eq!(runs("0 0 29 2 *", "2025-01-01 00:00", 1), ["Tue 2028-02-29 00:00"]);
+    }
+
+    #[test]
+    fn invalid_cron_expressions_are_rejected() {
+        let error = |expr: &str| CronSchedule::try_from(expr.to_string()).unwrap_err();
+        assert!(error("* * * *").contains("expected 5 fields"));
+        assert!(error("* * * * * *").contains("expected 5 fields"));
+        assert!(error("60 * * * *").contains("value 60 is out of range 0-59"));
+        assert!(error("* 24 * * *").contains("value 24 is out of range 0-23"));
+        assert!(error("* * 0 * *").contains("value 0 is out of range 1-31"));
+        assert!(error("* * 32 * *").contains("value 32 is out of range 1-31"));
+        assert!(error("* * * 13 *").contains("value 13 is out of range 1-12"));
+        assert!(error("* * * * 8").contains("value 8 is out of range 0-7"));
+        assert!(error("*/0 * * * *").contains("step must be at least 1"));
+        assert!(error("*/x * * * *").contains("invalid step 'x'"));
+        assert!(error("30-10 * * * *").contains("invalid range '30-10'"));
+        assert!(error("* * * * funday").contains("invalid value 'funday'"));
+        assert!(error("* * * jan-foo *").contains("invalid value 'foo'"));
+    }
+
+    /// Central European time: UTC+1, and UTC+2 from 2025-03-30 02:00 to
+    /// 2025-10-26 03:00 local time.
+    #[derive(Debug, Clone, Copy)]
+    struct Cet;
+
+    impl Cet {
+        fn winter() -> FixedOffset {
+            FixedOffset::east_opt(3600).unwrap()
+        }
+
+        fn summer() -> FixedOffset {
+            FixedOffset::east_opt(7200).unwrap()
+        }
+    }
+
+    impl TimeZone for Cet {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Cet
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let valid: Vec<FixedOffset> = [Cet::summer(), Cet::winter()]
+                .into_iter()
+                .filter(|offset| {
+                    let utc = *local - Duration::seconds(offset.local_minus_utc() as i64);
+                    self.offset_from_utc_datetime(&utc) == *offset
+                })
+                .collect();
+            match valid[..] {
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, time: &NaiveDateTime) -> FixedOffset {
+            let summer = utc("2025-03-30 01:00").naive_utc()..utc("2025-10-26 01:00").naive_utc();
+            if summer.contains(time) {
+                Cet::summer()
+            } else {
+                Cet::winter()
+            }
+        }
+    }
+
+    fn cet_runs(expr: &str, start: &str, n: usize) -> Vec<String> {
+        let schedule = cron(expr);
+        let mut now = utc(start).with_timezone(&Cet);
+        let mut runs = Vec::new();
+        for _ in 0..n {
+            now = schedule.next_after(&now).unwrap();
+            runs.push(now.format("%m-%d %H:%M %:z").to_string());
+        }
+        runs
+    }
+
+    #[test]
+    fn times_skipped_by_dst_do_not_run_that_day() {
+        assert_eq!(
+            cet_runs("30 2 * * *", "2025-03-28 12:00", 3),
+            ["03-29 02:30 +01:00", "03-31 02:30 +02:00", "04-01 02:30 +02:00"]
+        );
+        assert_eq!(
+            cet_runs("*/30 * * * *", "2025-03-30 00:10", 3),
+            ["03-30 01:30 +01:00", "03-30 03:00 +02:00", "03-30 03:30 +02:00"]
+        );
+    }
+
+    #[test]
+    fn times_repeated_by_dst_run_once() {
+        assert_eq!(
+            cet_runs("30 2 * * *", "2025-10-25 12:00", 3),
+            ["10-26 02:30 +02:00", "10-27 02:30 +01:00", "10-28 02:30 +01:00"]
+        );
+    }
+
+    fn window(start: &str, end: &str, days: &[&str]) -> TimeWindow {
+        TimeWindow {
+            start: Some(TimeOfDay::try_from(start.to_string()).unwrap()),
+            end: Some(TimeOfDay::try_from(end.to_string()).unwrap()),
+            days: days.iter().map(|d| Days::try_from(d.to_string()).unwrap()).collect(),
+        }
+    }
+
+    #[test]
+    fn windows_include_the_start_and_exclude_the_end() {
+        let day = window("09:00", "17:30", &[]);
+        assert!(!day.contains(&utc("2025-01-06 08:59")));
+        assert!(day.contains(&utc("2025-01-06 09:00")));
+        assert!(day.contains(&utc("2025-01-06 17:29")));
+        assert!(!day.contains(&utc("2025-01-06 17:30")));
+
+        let until_midnight = window("22:00", "24:00", &[]);
+        assert!(until_midnight.contains(&utc("2025-01-06 23:59")));
+        assert!(!until_midnight.contains(&utc("2025-01-07 00:00")));
+
+        let all_day = TimeWindow {
+            start: None,
+            end: None,
+            days: vec![Days::Weekends],
+        };
+        assert!(!all_day.contains(&utc("2025-01-10 23:59")));
+        assert!(all_day.contains(&utc("2025-01-11 00:00")));
+        assert!(all_day.contains(&utc("2025-01-12 23:59")));
+        assert!(!all_day.contains(&utc("2025-01-13 00:00")));
+    }
+
+    #[test]
+    fn windows_wrap_past_midnight_on_the_start_day() {
+        // 2025-01-10 is a Friday
+        let night = window("22:00", "07:00", &["fri"]);
+        assert!(!night.contains(&utc("2025-01-10 06:00")));
+        assert!(!night.contains(&utc("2025-01-10 21:59")));
+        assert!(night.contains(&utc("2025-01-10 22:00")));
+        assert!(night.contains(&utc("2025-01-11 06:59")));
+        assert!(!night.contains(&utc("2025-01-11 07:00")));
+        assert!(!night.contains(&utc("2025-01-11 22:00")));
+
+        let weeknights = window("22:00", "07:00", &["weekdays"]);
+        assert!(weeknights.contains(&utc("2025-01-11 06:00")));
+        assert!(!weeknights.contains(&utc("2025-01-12 06:00")));
+        assert!(!weeknights.contains(&utc("2025-01-11 22:00")));
+        assert!(!weeknights.contains(&utc("2025-01-13 06:00")));
+        assert!(weeknights.contains(&utc("2025-01-13 22:00")));
+    }
+
+    #[test]
+    fn invalid_times_and_days_are_rejected() {
+        assert!(TimeOfDay::try_from("24:01".to_string()).is_err());
+        assert!(TimeOfDay::try_from("7pm".to_string()).is_err());
+        assert_eq!(Days::try_from("Weekends".to_string()), Ok(Days::Weekends));
+        assert_eq!(Days::try_from("Tuesday".to_string()), Ok(Days::Day(Weekday::Tue)));
+        assert!(Days::try_from("someday".to_string()).is_err());
+    }
+}
@@ -1,36 +1,182 @@
-use crate::event::Event;
-use tokio::io::AsyncReadExt;
-use tokio::net::TcpListener;
-use tokio::sync::mpsc;
-
-/// Starts the TCP listener and sends received messages to the event channel.
-pub fn start(port: u16, tx: mpsc::UnboundedSender<Event>) {
-    tokio::spawn(async move {
-        let addr = format!("0.0.0.0:{}", port);
+use crate::config::ListenerConfig;
+use crate::event::Message;
+use crate::health::HealthRegistry;
+use crate::source::{Source, SourceContext};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info};
+
+/// Source name used for messages received by the listener.
+pub const SOURCE_NAME: &str = "Listener";
+
+/// Maximum size of a received message or webhook request.
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Receives messages on a TCP port.
+///
+/// Plain text is shown as is. HTTP `POST`/`PUT` requests (webhooks) are
+/// answered with `204 No Content` and their body is used as the message.
+/// JSON payloads are formatted with the listener's `json_keys`/`template`.
+/// `GET /status` returns the health of all sources as JSON. It is only
+/// served to clients on the same host, since the listener accepts
+/// connections from the network.
+///
+/// On shutdown the listening socket is closed and pending connections are
+/// dropped.
+pub struct ListenerSource {
+    port: u16,
+    config: Arc<ListenerConfig>,
+}
+
+impl ListenerSource {
+    pub fn new(port: u16, config: ListenerConfig) -> Self {
+        Self {
+            port,
+            config: Arc::new(config),
+        }
+    }
+
+    async fn listen(self, ctx: &SourceContext) {
+        let addr = format!("0.0.0.0:{}", self.port);
         let listener = match TcpListener::bind(&addr).await {
             Ok(l) => l,
             Err(e) => {
-                eprintln!("Failed to bind to {}: {}", addr, e);
+                error!(%addr, error = %e, "failed to bind listener");
+                ctx.health()
+                    .failure(SOURCE_NAME, format!("failed to bind to {}: {}", addr, e));
                 return;
             }
         };
+        ctx.success();
+        info!(%addr, "listening");
 
         loop {
-            if let Ok((mut socket, _)) = listener.accept().await {
-                let tx = tx.clone();
-                tokio::spawn(async move {
-                    let mut buf = vec![0; 1024];
-                    if let Ok(n) = socket.read(&mut buf).await {
-                        if n > 0 {
-                            let msg = String::from_utf8_lossy(&buf[..n]).to_string();
-                            let msg = msg.trim().to_string();
-                            if !msg.is_empty() {
-                                let _ = tx.send(Event::Message(msg));
-                            }
-                        }
+            if let Ok((socket, peer)) = listener.accept().await {
+                let conn = ctx.clone();
+                let config = self.config.clone();
+                ctx.spawn(async move {
+                    // While disabled, messages are read but discarded
+                    if let Some(payload) = read_payload(socket, peer, conn.health()).await
+                        && conn.is_enabled()
+                        && let Some(msg) = parse_message(&payload, &config)
+                    {
+                        conn.emit(msg);
                     }
                 });
             }
         }
-    });
+    }
+}
+
+impl Source for ListenerSource {
+    fn name(&self) -> &str {
+        SOURCE_NAME
+    }
+
+    fn kind(&self) -> &'static str {
+        "listener"
+    }
+
+    fn run(self: Box<Self>, ctx: SourceContext) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            tokio::select! {
+                _ = ctx.shutdown() => {}
+                _ = self.listen(&ctx) => {}
+            }
+        })
+    }
+}
+
+/// Reads a plain message, or the body of an HTTP request. Status queries are
+/// answered directly and yield no message.
+async fn read_payload(mut socket: TcpStream, peer: SocketAddr, health: &HealthRegistry) -> Option<String> {
+    let mut buf = vec![0; MAX_REQUEST_BYTES];
+    let mut len = socket.read(&mut buf).await.ok()?;
+    if len == 0 {
+        return None;
+    }
+
+    if buf.starts_with(b"GET /status ") || buf.starts_with(b"GET /status?") {
+        // Errors and values may reveal details of the sources
+        if !peer.ip().to_canonical().is_loopback() {
+            let _ = socket
+                .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+            return None;
+        }
+        let body = health.to_json().to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _ = socket.write_all(response.as_bytes()).await;
+        return None;
+    }
+
+    let is_http = buf.starts_with(b"POST ") || buf.starts_with(b"PUT ");
+    if !is_http {
+        return Some(String::from_utf8_lossy(&buf[..len]).to_string());
+    }
+
+    // Read until the headers and the whole body have arrived
+    loop {
+        let received = &buf[..len];
+        if let Some(header_end) = find_header_end(received) {
+            let body_len = content_length(&received[..header_end]).unwrap_or(0);
+            if len >= header_end + body_len || len == buf.len() {
+                let body_end = (header_end + body_len).min(len);
+                let body = String::from_utf8_lossy(&received[header_end..body_end]).to_string();
+                let _ = socket
+                    .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                    .await;
+                return Some(body);
+            }
+        }
+        if len == buf.len() {
+            return None;
+        }
+        match socket.read(&mut buf[len..]).await {
+            Ok(0) | Err(_) => return None,
+            Ok(n) => len += n,
+        }
+    }
+}
+
+/// Returns the offset of the body (just after the blank line).
+fn find_header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|i| i + 4)
+}
+
+fn content_length(headers: &[u8]) -> Option<usize> {
+    String::from_utf8_lossy(headers).lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+fn parse_message(payload: &str, config: &ListenerConfig) -> Option<Message> {
+    let payload = payload.trim();
+    if payload.is_empty() {
+        return None;
+    }
+
+    let Ok(json) = serde_json::from_str::<Value>(payload) else {
+        return Some(Message::new(SOURCE_NAME, payload, config.priority));
+    };
+
+    let text = if config.format.is_json() {
+        config.format.render(&json)?
+    } else {
+        payload.to_string()
+    };
+    Some(Message::new(SOURCE_NAME, text, config.priority).with_json(json))
 }
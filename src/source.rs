@@ -0,0 +1,248 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+use reqwest::Client;
+use serde_json::Value;
+use std::future::Future;
+use tracing::warn;
+
+use crate::api::ApiSource;
+use crate::clock::Clock;
+use crate::condition::Threshold;
+use crate::config::{ApiConfig, Config, WsConfig};
+use crate::event::{Message, Priority};
+use crate::health::HealthRegistry;
+use crate::items::ItemTracker;
+use crate::json::Path;
+use crate::queue::MessageSender;
+use crate::server::ListenerSource;
+use crate::shutdown::Shutdown;
+use crate::template::MessageFormat;
+use crate::ws_client::WsSource;
+
+/// A producer of messages, such as an API poller, a WebSocket client or the
+/// TCP listener.
+///
+/// Messages go through the same pipeline (rules, rate limiting, DND, history)
+/// whatever source they come from.
+pub trait Source: Send {
+    /// Name used as the message source and in health reports.
+    fn name(&self) -> &str;
+
+    /// Short description of the kind of source, shown in the status panel.
+    fn kind(&self) -> &'static str;
+
+    /// Whether the source starts enabled.
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    /// Runs the source until `ctx.shutdown()` completes. While disabled, the
+    /// source should produce nothing (see `SourceContext::wait_until_enabled`).
+    fn run(self: Box<Self>, ctx: SourceContext) -> BoxFuture<'static, ()>;
+}
+
+/// Handle given to a running source to emit messages and report its state.
+#[derive(Debug, Clone)]
+pub struct SourceContext {
+    name: String,
+    tx: MessageSender,
+    health: HealthRegistry,
+    shutdown: Shutdown,
+    clock: Clock,
+}
+
+impl SourceContext {
+    /// Sends a message to the app.
+    pub fn emit(&self, msg: Message) {
+        self.tx.send(msg);
+    }
+
+    /// Records a successful request or connection.
+    pub fn success(&self) {
+        self.health.success(&self.name);
+    }
+
+    /// Logs and records a failed request or connection.
+    pub fn failure(&self, error: &anyhow::Error) {
+        let error = format!("{:#}", error);
+        warn!(source = %self.name, error = %error, "source failed");
+        self.health.failure(&self.name, error);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.health.is_enabled(&self.name)
+    }
+
+    /// Waits until the source is enabled.
+    pub async fn wait_until_enabled(&self) {
+        self.health.wait_until_enabled(&self.name, true).await;
+    }
+
+    /// Completes when the source should stop producing: on shutdown or when
+    /// it is disabled.
+    pub async fn stopped(&self) {
+        tokio::select! {
+            _ = self.shutdown.cancelled() => {}
+            _ = self.health.wait_until_enabled(&self.name, false) => {}
+        }
+    }
+
+    /// Completes once shutdown has started.
+    pub async fn shutdown(&self) {
+        self.shutdown.cancelled().await;
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    /// Spawns a helper task (e.g. a connection handler) that is dropped on
+    /// shutdown.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown.spawn_until_cancelled(task);
+    }
+
+    /// Health of all sources.
+    pub fn health(&self) -> &HealthRegistry {
+        &self.health
+    }
+
+    /// Clock for schedules and timestamps. Sleeps should use tokio's timer.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+}
+
+/// The set of sources run by the app.
+#[derive(Default)]
+pub struct SourceRegistry {
+    sources: Vec<Box<dyn Source>>,
+    clock: Clock,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the listener and the API and WebSocket sources of `config`.
+    pub fn from_config(config: &Config) -> Self {
+        let mut registry = Self::new();
+        registry.add(ListenerSource::new(config.listen_port, config.listener.clone()));
+
+        let client = Client::new();
+        for api in &config.api_sources {
+            registry.add(ApiSource::new(api.clone(), client.clone()));
+        }
+        for ws in &config.ws_sources {
+            registry.add(WsSource::new(ws.clone()));
+        }
+        registry
+    }
+
+    /// Gives the sources `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn add(&mut self, source: impl Source + 'static) {
+        self.sources.push(Box::new(source));
+    }
+
+    /// Registers every source with `health` (disabled ones too, so they can
+    /// be enabled at runtime) and spawns it. Sources stop when `shutdown`
+    /// starts, and shutdown waits for them.
+    pub fn start(self, tx: MessageSender, health: HealthRegistry, shutdown: Shutdown) {
+        for source in self.sources {
+            health.register(source.name(), source.kind(), source.enabled());
+            let ctx = SourceContext {
+                name: source.name().to_string(),
+                tx: tx.clone(),
+                health: health.clone(),
+                shutdown: shutdown.clone(),
+                clock: self.clock.clone(),
+            };
+            shutdown.spawn(source.run(ctx));
+        }
+    }
+}
+
+/// How a payload received by an API or WebSocket source becomes messages.
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadSpec<'a> {
+    pub name: &'a str,
+    pub priority: Priority,
+    pub format: &'a MessageFormat,
+    pub items: Option<&'a Path>,
+    pub id_field: Option<&'a Path>,
+    pub threshold: Option<&'a Threshold>,
+}
+
+impl<'a> From<&'a ApiConfig> for PayloadSpec<'a> {
+    fn from(config: &'a ApiConfig) -> Self {
+        Self {
+            name: &config.name,
+            priority: config.priority,
+            format: &config.format,
+            items: config.items.as_ref(),
+            id_field: config.id_field.as_ref(),
+            threshold: config.threshold.as_ref(),
+        }
+    }
+}
+
+impl<'a> From<&'a WsConfig> for PayloadSpec<'a> {
+    fn from(config: &'a WsConfig) -> Self {
+        Self {
+            name: &config.name,
+            priority: config.priority,
+            format: &config.format,
+            items: config.items.as_ref(),
+            id_field: config.id_field.as_ref(),
+            threshold: config.threshold.as_ref(),
+        }
+    }
+}
+
+impl PayloadSpec<'_> {
+    /// Turns a payload into messages: one per new element selected by
+    /// `items`, one rendered message for JSON formats, or the raw text.
+    /// Messages not meeting the threshold are dropped (or rerouted).
+    ///
+    /// Fails if the payload should be JSON but is not.
+    pub fn messages(&self, payload: &[u8], tracker: &mut ItemTracker) -> Result<Vec<Message>> {
+        let messages = if let Some(items) = self.items {
+            let json: Value = serde_json::from_slice(payload)?;
+            tracker
+                .new_items(&json, items, self.id_field)
+                .into_iter()
+                .filter_map(|item| {
+                    let text = self.format.render(&item)?;
+                    Some(Message::labeled(self.name, text, self.priority).with_json(item))
+                })
+                .collect()
+        } else if self.format.is_json() {
+            let json: Value = serde_json::from_slice(payload)?;
+            match self.format.render(&json) {
+                Some(text) => vec![Message::labeled(self.name, text, self.priority).with_json(json)],
+                // Data was not found, ignore
+                None => vec![],
+            }
+        } else {
+            let text = String::from_utf8_lossy(payload);
+            vec![Message::labeled(self.name, text, self.priority)]
+        };
+
+        Ok(messages
+            .into_iter()
+            .filter_map(|msg| match self.threshold {
+                Some(threshold) => threshold.apply(msg),
+                None => Some(msg),
+            })
+            .collect())
+    }
+}
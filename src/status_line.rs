@@ -0,0 +1,235 @@
+use anyhow::Result;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::StatefulWidget,
+};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::App;
+use crate::event::{Event, EventHandler};
+use crate::ticker::Ticker;
+
+/// Prints the ticker as a single line of text instead of drawing it in a
+/// terminal, e.g. for `#(infotube --status-line)` in the tmux status bar.
+///
+/// The line is the window of the ticker that the UI would show in `width`
+/// columns, padded to exactly that width.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusLine {
+    width: u16,
+    tmux: bool,
+}
+
+impl StatusLine {
+    pub fn new(width: u16) -> Self {
+        Self { width, tmux: false }
+    }
+
+    /// Wraps interrupts in tmux style codes (`#[fg=red]...#[default]`) with
+    /// the alert colors, and escapes `#` in the text.
+    pub fn tmux(mut self, tmux: bool) -> Self {
+        self.tmux = tmux;
+        self
+    }
+
+    /// The current line of `app`.
+    pub fn render(&self, app: &mut App) -> String {
+        let mut buf = Buffer::empty(Rect::new(0, 0, self.width, 1));
+        Ticker::new().render(buf.area, &mut buf, &mut app.ticker);
+
+        let mut line = String::new();
+        let mut x = 0;
+        while x < self.width {
+            let symbol = buf[(x, 0)].symbol();
+            line.push_str(symbol);
+            // Wide characters cover the next cell too
+            x += symbol.width().max(1) as u16;
+        }
+
+        if !self.tmux {
+            return line;
+        }
+        let line = line.replace('#', "##");
+        if app.ticker.interrupt.is_some() {
+            format!("#[{}]{}#[default]", tmux_style(app.ticker.alert_style), line)
+        } else {
+            line
+        }
+    }
+
+    /// Prints one line and returns, without starting the sources.
+    ///
+    /// Text that does not fit is scrolled by the time of day, one column per
+    /// `scroll_speed_ms`, so that a line printed on every status bar refresh
+    /// moves along.
+    pub fn print_once(&self, app: &mut App) -> Result<()> {
+        app.handle_event(Event::Resize(self.width));
+        let millis = app.clock().now().timestamp_millis().max(0) as u64;
+        app.ticker.offset = (millis / app.config.scroll_speed_ms.max(1)) as usize;
+
+        let mut stdout = io::stdout();
+        writeln!(stdout, "{}", self.render(app))?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Applies events like `App::run` and prints a new line whenever it
+    /// changes, until a signal stops the app or stdout is closed.
+    pub async fn run(&self, app: &mut App, events: &mut EventHandler) -> Result<()> {
+        app.handle_event(Event::Resize(self.width));
+        let mut tick_rate = app.config.scroll_speed_ms;
+        let mut last_line = None;
+
+        while app.running {
+            let line = self.render(app);
+            if last_line.as_ref() != Some(&line) {
+                let mut stdout = io::stdout();
+                match writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+                    Ok(()) => {}
+                    // The reader (e.g. tmux) has gone away
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => break,
+                    Err(e) => return Err(e.into()),
+                }
+                last_line = Some(line);
+            }
+
+            match events.next().await {
+                Some(event) => app.handle_event(event),
+                None => break,
+            }
+
+            // Config reloads change the animation speed
+            if app.config.scroll_speed_ms != tick_rate {
+                tick_rate = app.config.scroll_speed_ms;
+                events.set_tick_rate(tick_rate);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// tmux style (`fg=red,bg=default`) for the colors of `style`.
+fn tmux_style(style: Style) -> String {
+    format!(
+        "fg={},bg={}",
+        tmux_color(style.fg.unwrap_or(Color::Reset)),
+        tmux_color(style.bg.unwrap_or(Color::Reset))
+    )
+}
+
+fn tmux_color(color: Color) -> String {
+    let name = match color {
+        Color::Reset => "default",
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        // ratatui's Gray and White are ANSI white and bright white
+        Color::Gray => "white",
+        Color::DarkGray => "brightblack",
+        Color::LightRed => "brightred",
+        Color::LightGreen => "brightgreen",
+        Color::LightYellow => "brightyellow",
+        Color::LightBlue => "brightblue",
+        Color::LightMagenta => "brightmagenta",
+        Color::LightCyan => "brightcyan",
+        Color::White => "brightwhite",
+        Color::Indexed(i) => return format!("colour{}", i),
+        Color::Rgb(r, g, b) => return format!("#{:02x}{:02x}{:02x}", r, g, b),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::config::Config;
+    use crate::event::{Message, MessageStyle, Priority};
+    use crate::health::HealthRegistry;
+    use crate::logging::LogBuffer;
+    use chrono::{Local, TimeZone};
+
+    fn app(text: &str) -> App {
+        App::new(Config::default(), HealthRegistry::new(), LogBuffer::default()).with_text(text)
+    }
+
+    fn alert(text: &str) -> Event {
+        Event::Message(Message::new("test", text, Priority::High))
+    }
+
+    #[test]
+    fn line_is_padded_to_the_width() {
+        let mut app = app("Hello");
+        assert_eq!(StatusLine::new(8).render(&mut app), "Hello   ");
+    }
+
+    #[test]
+    fn long_text_shows_the_scrolled_window() {
+        let mut app = app("日本語のテキスト");
+        app.handle_event(Event::Resize(6));
+        app.handle_event(Event::Tick);
+        app.handle_event(Event::Tick);
+        assert_eq!(StatusLine::new(6).render(&mut app), "本語の");
+    }
+
+    #[test]
+    fn tmux_codes_color_only_alerts() {
+        let status_line = StatusLine::new(12).tmux(true);
+        let mut app = app("Issue #42");
+        assert_eq!(status_line.render(&mut app), "Issue ##42   ");
+
+        let mut style = Message::new("test", "Down!", Priority::High);
+        style.style = Some(MessageStyle {
+            fg: Some("LightYellow".to_string()),
+            bg: Some("#003366".to_string()),
+        });
+        app.handle_event(Event::Message(style));
+        assert_eq!(
+            status_line.render(&mut app),
+            "#[fg=brightyellow,bg=#003366](9s)  Down! #[default]"
+        );
+    }
+
+    #[test]
+    fn interrupt_counts_down_and_ends() {
+        let config = Config {
+            interrupt_duration_sec: 1,
+            ..Config::default()
+        };
+        let mut app = App::new(config, HealthRegistry::new(), LogBuffer::default()).with_text("Hello");
+        app.handle_event(Event::Resize(16));
+        app.handle_event(alert("Alert!"));
+        let status_line = StatusLine::new(16).tmux(true);
+        assert_eq!(
+            status_line.render(&mut app),
+            "#[fg=red,bg=default](1s)  Alert!    #[default]"
+        );
+
+        // Ticks are 100ms apart
+        for _ in 0..10 {
+            app.handle_event(Event::Tick);
+        }
+        assert_eq!(status_line.render(&mut app), "Hello           ");
+    }
+
+    #[test]
+    fn print_once_scrolls_by_the_time_of_day() {
+        let clock = Clock::manual(Local.with_ymd_and_hms(2025, 1, 6, 9, 0, 0).unwrap());
+        let mut app = app("abcdefghij").with_clock(clock.clone());
+        let status_line = StatusLine::new(4);
+
+        status_line.print_once(&mut app).unwrap();
+        let first = app.ticker.offset;
+        clock.advance(std::time::Duration::from_millis(300));
+        status_line.print_once(&mut app).unwrap();
+        // "abcdefghij   ***   " is 19 columns wide
+        assert_eq!(app.ticker.offset, (first + 3) % 19);
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Deserializer, de};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::json::{self, Path};
+
+/// A filter applied to a placeholder value.
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// Text used when the field is missing.
+    Default(String),
+    Upper,
+    Lower,
+    /// Number with a fixed number of decimal places.
+    Fixed(usize),
+    /// Timestamp reformatted with a strftime format.
+    Time(String),
+}
+
+impl Filter {
+    fn parse(s: &str) -> Result<Self, String> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg)),
+            None => (s.trim(), None),
+        };
+        match (name, arg) {
+            ("default", Some(arg)) => Ok(Filter::Default(arg.to_string())),
+            ("upper", None) => Ok(Filter::Upper),
+            ("lower", None) => Ok(Filter::Lower),
+            ("fixed", Some(arg)) => arg
+                .trim()
+                .parse()
+                .map(Filter::Fixed)
+                .map_err(|_| format!("invalid precision '{}' for fixed", arg)),
+            ("time", Some(arg)) => {
+                // Formatting with an invalid item panics, so reject it here
+                if StrftimeItems::new(arg).any(|item| item == Item::Error) {
+                    return Err(format!("invalid time format '{}'", arg));
+                }
+                Ok(Filter::Time(arg.to_string()))
+            }
+            _ => Err(format!("unknown filter '{}'", s)),
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Filter::Default(_) => value,
+            Filter::Upper => value.to_uppercase(),
+            Filter::Lower => value.to_lowercase(),
+            Filter::Fixed(precision) => match value.trim().parse::<f64>() {
+                Ok(n) => format!("{:.*}", precision, n),
+                Err(_) => value,
+            },
+            Filter::Time(format) => match parse_timestamp(&value) {
+                Some(time) => time.format(format).to_string(),
+                None => value,
+            },
+        }
+    }
+}
+
+/// Parses RFC 3339, common `Y/m/d H:M:S` forms and Unix timestamps
+/// (seconds or milliseconds) as local time.
+fn parse_timestamp(s: &str) -> Option<DateTime<Local>> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Some(time.with_timezone(&Local));
+    }
+    for format in [
+        "%Y/%m/%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+    ] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(s, format) {
+            return Local.from_local_datetime(&time).earliest();
+        }
+    }
+    let epoch = s.parse::<i64>().ok()?;
+    if epoch > 100_000_000_000 {
+        Local.timestamp_millis_opt(epoch).single()
+    } else {
+        Local.timestamp_opt(epoch, 0).single()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    /// `name` refers to a named path in `fields`, or else is `path` itself.
+    Field {
+        name: String,
+        path: Path,
+        filters: Vec<Filter>,
+    },
+}
+
+/// A message template such as `"{hypocenter} M{magnitude|fixed:1}"`.
+///
+/// Placeholders name a field (or a JSON path) followed by `|`-separated
+/// filters: `default:TEXT`, `upper`, `lower`, `fixed:N` and `time:FORMAT`.
+/// `{{` and `}}` produce literal braces.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl TryFrom<String> for Template {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(format!("unclosed '{{' in template '{}'", s)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    let mut segments = placeholder.split('|');
+                    let name = segments.next().unwrap_or_default().trim().to_string();
+                    if name.is_empty() {
+                        return Err(format!("empty placeholder in template '{}'", s));
+                    }
+                    let path = Path::parse(&name)?;
+                    let filters = segments.map(Filter::parse).collect::<Result<_, _>>()?;
+                    parts.push(Part::Field { name, path, filters });
+                }
+                '}' => return Err(format!("unmatched '}}' in template '{}'", s)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl Template {
+    /// Renders the template. Names are looked up in `fields` first and
+    /// otherwise used as paths; multiple matches are joined with `, `.
+    /// Returns `None` if a field without a default is missing.
+    pub fn render(&self, value: &Value, fields: &HashMap<String, Path>) -> Option<String> {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field { name, path, filters } => {
+                    let path = fields.get(name).unwrap_or(path);
+                    let values: Vec<String> = path
+                        .select(value)
+                        .iter()
+                        .filter_map(|v| json::value_to_string(v))
+                        .collect();
+                    let found = (!values.is_empty()).then(|| values.join(", "));
+                    let text = match found {
+                        Some(text) => filters.iter().fold(text, |text, f| f.apply(text)),
+                        None => filters.iter().find_map(|f| match f {
+                            Filter::Default(default) => Some(default.clone()),
+                            _ => None,
+                        })?,
+                    };
+                    out.push_str(&text);
+                }
+            }
+        }
+        Some(out)
+    }
+}
+
+/// How display text is built from a JSON payload.
+///
+/// The struct is flattened into the source configs, which hides the key of
+/// an error, so errors are prefixed with it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MessageFormat {
+    /// Paths whose values are joined with a space
+    #[serde(default, deserialize_with = "json_keys")]
+    pub json_keys: Option<Vec<Path>>,
+    /// Named paths usable in the template
+    #[serde(default, deserialize_with = "fields")]
+    pub fields: HashMap<String, Path>,
+    /// Template for the display text (takes precedence over `json_keys`)
+    #[serde(default, deserialize_with = "template")]
+    pub template: Option<Template>,
+}
+
+fn keyed<'de, D: Deserializer<'de>, T: Deserialize<'de>>(key: &str, deserializer: D) -> Result<T, D::Error> {
+    T::deserialize(deserializer).map_err(|e| de::Error::custom(format!("{}: {}", key, e)))
+}
+
+fn json_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<Path>>, D::Error> {
+    keyed("json_keys", deserializer)
+}
+
+fn fields<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, Path>, D::Error> {
+    keyed("fields", deserializer)
+}
+
+fn template<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Template>, D::Error> {
+    keyed("template", deserializer)
+}
+
+impl MessageFormat {
+    /// Returns true if payloads should be parsed as JSON.
+    pub fn is_json(&self) -> bool {
+        self.json_keys.is_some() || self.template.is_some()
+    }
+
+    /// Builds the display text from a JSON payload.
+    pub fn render(&self, value: &Value) -> Option<String> {
+        if let Some(template) = &self.template {
+            template.render(value, &self.fields)
+        } else if let Some(keys) = &self.json_keys {
+            json::extract_message(value, keys)
+        } else {
+            json::value_to_string(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn template(s: &str) -> Template {
+        Template::try_from(s.to_string()).unwrap()
+    }
+
+    fn render(s: &str, value: &Value) -> Option<String> {
+        template(s).render(value, &HashMap::new())
+    }
+
+    #[test]
+    fn fields_and_paths_are_substituted() {
+        let value = json!({"place": "Tokyo", "quakes": [{"mag": 4}, {"mag": 5}]});
+        let mut fields = HashMap::new();
+        fields.insert("where".to_string(), Path::parse("place").unwrap());
+        let text = template("{where}: M{$.quakes[*].mag}").render(&value, &fields);
+        assert_eq!(text.as_deref(), Some("Tokyo: M4, 5"));
+    }
+
+    #[test]
+    fn missing_field_drops_the_message_unless_defaulted() {
+        let value = json!({"a": 1});
+        assert_eq!(render("{a} {b}", &value), None);
+        assert_eq!(render("{a} {b|default:n/a}", &value).as_deref(), Some("1 n/a"));
+    }
+
+    #[test]
+    fn filters_are_applied_in_order() {
+        let value = json!({"name": "Tokyo", "mag": 5.04, "text": "x"});
+        assert_eq!(render("{name|upper}", &value).as_deref(), Some("TOKYO"));
+        assert_eq!(render("{name|lower}", &value).as_deref(), Some("tokyo"));
+        assert_eq!(render("{mag|fixed:1}", &value).as_deref(), Some("5.0"));
+        assert_eq!(render("{mag|fixed:0}", &value).as_deref(), Some("5"));
+        // Non-numbers are left alone
+        assert_eq!(render("{text|fixed:2|upper}", &value).as_deref(), Some("X"));
+    }
+
+    #[test]
+    fn time_filter_reformats_timestamps() {
+        let value = json!({"at": "2025/01/06 09:05:00", "bad": "soon"});
+        assert_eq!(render("{at|time:%H:%M}", &value).as_deref(), Some("09:05"));
+        assert_eq!(render("{bad|time:%H:%M}", &value).as_deref(), Some("soon"));
+
+        let epoch = Local.with_ymd_and_hms(2025, 1, 6, 9, 5, 0).unwrap().timestamp();
+        let value = json!({"s": epoch, "ms": epoch * 1000});
+        assert_eq!(render("{s|time:%d %H:%M}", &value).as_deref(), Some("06 09:05"));
+        assert_eq!(render("{ms|time:%d %H:%M}", &value).as_deref(), Some("06 09:05"));
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        let value = json!({"a": 1});
+        assert_eq!(render("{{a}} = {a}", &value).as_deref(), Some("{a} = 1"));
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for s in [
+            "{a",
+            "a}",
+            "{}",
+            "{ |upper}",
+            "{a|shout}",
+            "{a|fixed:x}",
+            "{a|default}",
+            "{a|time:%Q}",
+            "{$.a[}",
+        ] {
+            assert!(Template::try_from(s.to_string()).is_err(), "{} was accepted", s);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::Style,
+    widgets::{Block, Paragraph, StatefulWidget, Widget},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Separator between the end and the start of scrolling text.
+const SPACER: &str = "   ***   ";
+
+/// A message shown over the ticker content for a limited time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interrupt {
+    pub text: String,
+    pub remaining_ms: usize,
+}
+
+impl Interrupt {
+    /// Countdown shown before the text, e.g. `(9s)  `.
+    pub fn prefix(&self) -> String {
+        let seconds = (self.remaining_ms as f64 / 1000.0).ceil() as usize;
+        format!("({}s)  ", seconds)
+    }
+}
+
+/// State of a [`Ticker`]: the text, the scroll position and the interrupt
+/// shown over it.
+///
+/// Call [`TickerState::tick`] on every animation step to scroll the text and
+/// count the interrupt down.
+#[derive(Debug, Clone, Default)]
+pub struct TickerState {
+    /// Text scrolled when no interrupt is shown.
+    pub content: String,
+    /// Scroll position, in columns.
+    pub offset: usize,
+    /// Style of the content.
+    pub style: Style,
+    /// Style used while an interrupt is shown.
+    pub alert_style: Style,
+    pub interrupt: Option<Interrupt>,
+    /// Position of the content before the interrupt, restored afterwards.
+    saved_offset: usize,
+}
+
+impl TickerState {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Shows `text` over the content for `duration_ms`, replacing any
+    /// interrupt already shown.
+    pub fn interrupt(&mut self, text: impl Into<String>, duration_ms: usize) {
+        // Keep the position from before the first interrupt
+        if self.interrupt.is_none() {
+            self.saved_offset = self.offset;
+        }
+        self.interrupt = Some(Interrupt {
+            text: text.into(),
+            remaining_ms: duration_ms,
+        });
+        self.offset = 0;
+    }
+
+    /// Closes the interrupt and returns to the content where it was.
+    pub fn dismiss(&mut self) {
+        if self.interrupt.take().is_some() {
+            self.offset = self.saved_offset;
+        }
+    }
+
+    /// Advances by one animation step of `elapsed_ms`, for a ticker `width`
+    /// columns wide. Text that fits is not scrolled.
+    ///
+    /// Returns true if the interrupt ended.
+    pub fn tick(&mut self, elapsed_ms: usize, width: usize) -> bool {
+        if let Some(interrupt) = &mut self.interrupt {
+            if interrupt.remaining_ms <= elapsed_ms {
+                self.dismiss();
+                return true;
+            }
+            interrupt.remaining_ms -= elapsed_ms;
+
+            let line_width = interrupt.prefix().width() + interrupt.text.width();
+            if line_width > width {
+                self.offset += 1;
+            }
+            return false;
+        }
+
+        if self.content.width() > width {
+            self.offset += 1;
+        } else {
+            self.offset = 0;
+        }
+        false
+    }
+
+    /// Style of what is currently shown.
+    pub fn current_style(&self) -> Style {
+        if self.interrupt.is_some() {
+            self.alert_style
+        } else {
+            self.style
+        }
+    }
+}
+
+/// A single line of text that scrolls when it does not fit, with an
+/// optional interrupt and countdown shown in its place.
+///
+/// ```
+/// use infotube::{Ticker, TickerState};
+/// use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+///
+/// let mut state = TickerState::new("Hello, world");
+/// let mut buf = Buffer::empty(Rect::new(0, 0, 8, 1));
+/// Ticker::new().render(buf.area, &mut buf, &mut state);
+/// assert_eq!(buf, Buffer::with_lines(["Hello, w"]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Ticker<'a> {
+    block: Option<Block<'a>>,
+    centered: bool,
+}
+
+impl<'a> Ticker<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps the ticker in a block (borders, title).
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Centers content that fits. Interrupts are always left-aligned.
+    pub fn centered(mut self, centered: bool) -> Self {
+        self.centered = centered;
+        self
+    }
+}
+
+impl StatefulWidget for Ticker<'_> {
+    type State = TickerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let width = match &self.block {
+            Some(block) => block.inner(area).width,
+            None => area.width,
+        } as usize;
+
+        let (prefix, content) = match &state.interrupt {
+            Some(interrupt) => (interrupt.prefix(), interrupt.text.as_str()),
+            None => (String::new(), state.content.as_str()),
+        };
+        let available_width = width.saturating_sub(prefix.width());
+
+        let alignment = if state.interrupt.is_none() && self.centered && content.width() <= width {
+            Alignment::Center
+        } else {
+            Alignment::Left
+        };
+
+        let mut line = prefix;
+        if content.width() <= available_width {
+            line.push_str(content);
+        } else {
+            let looped = format!("{}{}", content, SPACER);
+            state.offset %= looped.width();
+            push_window(&mut line, &looped, state.offset, available_width);
+        }
+
+        let mut paragraph = Paragraph::new(line)
+            .alignment(alignment)
+            .style(state.current_style());
+        if let Some(block) = self.block {
+            paragraph = paragraph.block(block);
+        }
+        paragraph.render(area, buf);
+    }
+}
+
+/// Appends `width` columns of `text`, repeated endlessly, starting at
+/// column `offset`.
+fn push_window(out: &mut String, text: &str, offset: usize, width: usize) {
+    let mut chars = text.chars().cycle();
+
+    // Skip to the character covering the offset
+    let mut skipped_width = 0;
+    let mut current_width = 0;
+    for c in chars.by_ref() {
+        let w = c.width().unwrap_or(0);
+        if skipped_width + w > offset {
+            out.push(c);
+            current_width += w;
+            break;
+        }
+        skipped_width += w;
+    }
+
+    for c in chars {
+        if current_width >= width {
+            break;
+        }
+        out.push(c);
+        current_width += c.width().unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+    use ratatui::widgets::Borders;
+
+    fn render(ticker: Ticker, state: &mut TickerState, width: u16, height: u16) -> Buffer {
+        let mut buf = Buffer::empty(Rect::new(0, 0, width, height));
+        ticker.render(buf.area, &mut buf, state);
+        buf
+    }
+
+    #[test]
+    fn short_text_is_left_aligned_by_default() {
+        let mut state = TickerState::new("Hi");
+        let buf = render(Ticker::new(), &mut state, 6, 1);
+        assert_eq!(buf, Buffer::with_lines(["Hi    "]));
+    }
+
+    #[test]
+    fn short_text_is_centered_in_a_block() {
+        let mut state = TickerState::new("Hi");
+        let ticker = Ticker::new()
+            .block(Block::default().borders(Borders::ALL).title("T"))
+            .centered(true);
+        let buf = render(ticker, &mut state, 8, 3);
+        assert_eq!(
+            buf,
+            Buffer::with_lines(["┌T─────┐", "│  Hi  │", "└──────┘"])
+        );
+    }
+
+    #[test]
+    fn long_text_scrolls_and_wraps_around() {
+        let mut state = TickerState::new("abcdef");
+        state.offset = 3;
+        let buf = render(Ticker::new(), &mut state, 5, 1);
+        assert_eq!(buf, Buffer::with_lines(["def  "]));
+
+        // "abcdef   ***   " is 15 columns wide
+        state.offset = 13;
+        let buf = render(Ticker::new(), &mut state, 5, 1);
+        assert_eq!(buf, Buffer::with_lines(["  abc"]));
+
+        state.offset = 15 + 1;
+        render(Ticker::new(), &mut state, 5, 1);
+        assert_eq!(state.offset, 1);
+    }
+
+    #[test]
+    fn wide_characters_scroll_by_column() {
+        let mut state = TickerState::new("日本語テキスト");
+        state.offset = 2;
+        let buf = render(Ticker::new(), &mut state, 6, 1);
+        assert_eq!(buf, Buffer::with_lines(["本語テ"]));
+    }
+
+    #[test]
+    fn interrupt_shows_countdown_in_alert_style() {
+        let mut state = TickerState::new("content");
+        state.alert_style = Style::default().fg(Color::Red);
+        state.interrupt("Alert!", 2500);
+
+        let buf = render(Ticker::new().centered(true), &mut state, 14, 1);
+        let mut expected = Buffer::with_lines(["(3s)  Alert!  "]);
+        expected.set_style(expected.area, Style::default().fg(Color::Red));
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn tick_counts_interrupt_down_and_restores_offset() {
+        let mut state = TickerState::new("a long piece of content");
+        state.offset = 4;
+        state.interrupt("Alert!", 250);
+
+        assert!(!state.tick(100, 10));
+        assert_eq!(state.interrupt.as_ref().unwrap().remaining_ms, 150);
+        assert!(!state.tick(100, 10));
+        assert!(state.tick(100, 10));
+        assert_eq!(state.interrupt, None);
+        assert_eq!(state.offset, 4);
+    }
+
+    #[test]
+    fn tick_only_scrolls_text_that_does_not_fit() {
+        let mut state = TickerState::new("short");
+        state.tick(100, 10);
+        assert_eq!(state.offset, 0);
+
+        state.content = "much longer than ten".to_string();
+        state.tick(100, 10);
+        state.tick(100, 10);
+        assert_eq!(state.offset, 2);
+    }
+}
@@ -1,25 +1,82 @@
 use anyhow::Result;
 use crossterm::{
+    cursor::Show,
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, Stdout};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
+/// Whether the terminal is currently in raw mode on the alternate screen.
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
 pub fn init() -> Result<Tui> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    enter()?;
+    let backend = CrosstermBackend::new(io::stdout());
     let terminal = Terminal::new(backend)?;
     Ok(terminal)
 }
 
 pub fn restore(terminal: &mut Tui) -> Result<()> {
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    leave()?;
     terminal.show_cursor()?;
     Ok(())
 }
+
+/// Restores the terminal before a panic is printed, so the message is
+/// readable and the shell is usable afterwards.
+///
+/// Panics in background tasks do not end the application; they are only
+/// logged, since printing them would corrupt the display.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        tracing::error!(panic = %info, "panic");
+        // The TUI runs on the main thread; tokio workers have their own names
+        if thread::current().name() == Some("main") {
+            let _ = leave();
+            default_hook(info);
+        }
+    }));
+}
+
+/// Leaves the TUI, stops the process (as Ctrl-Z would in a normal program)
+/// and takes over the terminal again once it is resumed with `fg`.
+#[cfg(unix)]
+pub fn suspend(terminal: &mut Tui) -> Result<()> {
+    use nix::sys::signal::{raise, Signal};
+
+    restore(terminal)?;
+    raise(Signal::SIGTSTP)?;
+    // Execution continues here after SIGCONT
+    enter()?;
+    terminal.clear()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn suspend(_terminal: &mut Tui) -> Result<()> {
+    Ok(())
+}
+
+fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Leaves raw mode and the alternate screen; does nothing if already left.
+fn leave() -> io::Result<()> {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, Show)?;
+    Ok(())
+}
@@ -1,17 +1,32 @@
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, Borders, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Cell, List, ListItem, Row, Table, TableState},
     Frame,
 };
 use std::str::FromStr;
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::app::App;
 use crate::config::Config;
+use crate::event::MessageStyle;
+use crate::health::SourceHealth;
+use crate::ticker::Ticker;
 
-pub fn draw(f: &mut Frame, app: &App) {
-    let area = f.area();
+/// Draws the app over the whole frame.
+pub fn draw(f: &mut Frame, app: &mut App) {
+    render(f, app, f.area());
+}
+
+/// Draws the app into `area`, e.g. one cell of a larger layout.
+pub fn render(f: &mut Frame, app: &mut App, area: Rect) {
+    if app.show_status {
+        render_status(f, app, area);
+        return;
+    }
+    if app.show_logs {
+        render_logs(f, app, area);
+        return;
+    }
 
     // Determine target area based on frame config
     let target_area = if !app.config.show_frame {
@@ -23,131 +38,184 @@ pub fn draw(f: &mut Frame, app: &App) {
         area
     };
 
-    let is_alert = app.interrupt_text.is_some();
-    let style = get_style(&app.config, is_alert, app.dimmed);
-
-    let (block, inner_area) = if app.config.show_frame {
-        let title = get_title(is_alert, app.paused);
-        let b = Block::default()
-            .borders(Borders::ALL)
-            .title(title)
-            .style(style);
-        let inner = b.inner(target_area);
-        (Some(b), inner)
-    } else {
-        (None, target_area)
-    };
-
-    let paragraph = render_ticker(app, inner_area.width as usize, style, is_alert);
-    
-    // Apply block if exists
-    let widget = if let Some(b) = block {
-        paragraph.block(b)
-    } else {
-        paragraph
-    };
+    let mut ticker = Ticker::new().centered(app.config.show_frame);
+    if app.config.show_frame {
+        let title = get_title(
+            app.ticker.interrupt.is_some(),
+            app.paused,
+            app.dnd_active,
+            app.show_history,
+            app.failing_sources(),
+        );
+        ticker = ticker.block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .style(app.ticker.current_style()),
+        );
+    }
 
-    f.render_widget(widget, target_area);
+    f.render_stateful_widget(ticker, target_area, &mut app.ticker);
 }
 
-fn get_style(config: &Config, is_alert: bool, dimmed: bool) -> Style {
-    let parse_color = |s: &str, default: Color| -> Color {
-        if s.eq_ignore_ascii_case("None") {
-            Color::Reset
-        } else {
-            Color::from_str(s).unwrap_or(default)
-        }
-    };
-
-    let fg_default = parse_color(&config.colors.fg_default, Color::White);
-    let bg_default = parse_color(&config.colors.bg_default, Color::Reset);
-    let fg_alert = parse_color(&config.colors.fg_alert, Color::Red);
-    let bg_alert = parse_color(&config.colors.bg_alert, Color::Reset);
+fn parse_color(s: &str, default: Color) -> Color {
+    if s.eq_ignore_ascii_case("None") {
+        Color::Reset
+    } else {
+        Color::from_str(s).unwrap_or(default)
+    }
+}
 
-    let fg = if is_alert {
-        fg_alert
-    } else if dimmed {
+/// Style of the ticker text and panels.
+pub fn base_style(config: &Config, dimmed: bool) -> Style {
+    let fg = if dimmed {
         Color::DarkGray
     } else {
-        fg_default
+        parse_color(&config.colors.fg_default, Color::White)
     };
-
-    let bg = if is_alert { bg_alert } else { bg_default };
-
+    let bg = parse_color(&config.colors.bg_default, Color::Reset);
     Style::default().fg(fg).bg(bg)
 }
 
-fn get_title(is_alert: bool, paused: bool) -> &'static str {
+/// Style of interrupts: the colors requested by the message, or the
+/// configured alert colors.
+pub fn alert_style(config: &Config, override_style: Option<&MessageStyle>) -> Style {
+    let fg = override_style
+        .and_then(|s| s.fg.as_deref())
+        .unwrap_or(&config.colors.fg_alert);
+    let bg = override_style
+        .and_then(|s| s.bg.as_deref())
+        .unwrap_or(&config.colors.bg_alert);
+    Style::default()
+        .fg(parse_color(fg, Color::Red))
+        .bg(parse_color(bg, Color::Reset))
+}
+
+fn get_title(is_alert: bool, paused: bool, dnd: bool, history: bool, failing: usize) -> String {
+    let mut title = String::from("Infotube");
     if is_alert {
-        if paused {
-            "Infotube - ALERT (Paused)"
-        } else {
-            "Infotube - ALERT"
-        }
-    } else if paused {
-        "Infotube (Paused)"
-    } else {
-        "Infotube"
+        title.push_str(" - ALERT");
+    }
+    if paused {
+        title.push_str(" (Paused)");
+    }
+    if dnd {
+        title.push_str(" (DND)");
+    }
+    if history {
+        title.push_str(" - History");
     }
+    if failing > 0 {
+        title.push_str(&format!(" [{} source(s) failing]", failing));
+    }
+    title
 }
 
-fn render_ticker(app: &App, width: usize, style: Style, is_alert: bool) -> Paragraph<'static> {
-    let (prefix, content_text) = if let Some(ref text) = app.interrupt_text {
-        let seconds = (app.interrupt_remaining_ms as f64 / 1000.0).ceil() as usize;
-        (format!("({}s)  ", seconds), text.as_str())
+/// Draws the source status panel over the whole area.
+fn render_status(f: &mut Frame, app: &App, area: Rect) {
+    let style = base_style(&app.config, app.dimmed);
+    let sources = app.health.snapshot();
+
+    let header = Row::new([
+        "Source",
+        "Kind",
+        "Enabled",
+        "State",
+        "Last fetch",
+        "Errors",
+        "Dropped",
+        "Last value / error",
+    ])
+        .style(style.add_modifier(Modifier::BOLD));
+    let rows = sources.iter().map(|(name, health)| status_row(name, health));
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Length(8),
+        Constraint::Length(7),
+        Constraint::Length(9),
+        Constraint::Length(10),
+        Constraint::Length(6),
+        Constraint::Length(7),
+        Constraint::Min(10),
+    ];
+
+    let mut table = Table::new(rows, widths)
+        .header(header)
+        .style(style)
+        .row_highlight_style(style.add_modifier(Modifier::REVERSED));
+    if app.config.show_frame {
+        table = table.block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Infotube - Sources (e: enable/disable, s: close)")
+                .style(style),
+        );
+    }
+
+    let mut state = TableState::default().with_selected(Some(app.status_selected));
+    f.render_stateful_widget(table, area, &mut state);
+}
+
+fn status_row<'a>(name: &'a str, health: &'a SourceHealth) -> Row<'a> {
+    let state = if health.enabled {
+        health.state.as_str()
+    } else {
+        "-"
+    };
+    let last_fetch = health
+        .last_message
+        .max(health.last_success)
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string());
+    // A failing source shows why; otherwise the last received text
+    let detail = if health.consecutive_failures > 0 {
+        health.last_error.as_deref()
     } else {
-        (String::new(), app.text.as_str())
+        health.last_value.as_deref()
     };
 
-    let prefix_width = prefix.width();
-    let content_available_width = width.saturating_sub(prefix_width);
-    let content_text_width = content_text.width();
+    Row::new([
+        Cell::from(name),
+        Cell::from(health.kind),
+        Cell::from(if health.enabled { "yes" } else { "no" }),
+        Cell::from(state),
+        Cell::from(last_fetch),
+        Cell::from(health.error_count.to_string()),
+        Cell::from(health.dropped.to_string()),
+        Cell::from(detail.unwrap_or("-")),
+    ])
+}
 
-    let mut displayed_string = String::from(&prefix);
+/// Draws recent warnings and errors, newest first, over the whole area.
+fn render_logs(f: &mut Frame, app: &App, area: Rect) {
+    let style = base_style(&app.config, app.dimmed);
+    let entries = app.logs.entries();
 
-    let alignment = if is_alert {
-        Alignment::Left
-    } else if content_text_width <= width && app.config.show_frame {
-        Alignment::Center
+    let items: Vec<ListItem> = if entries.is_empty() {
+        vec![ListItem::new("No warnings.")]
     } else {
-        Alignment::Left
+        entries
+            .iter()
+            .map(|entry| {
+                ListItem::new(format!(
+                    "{} {:5} {}: {}",
+                    entry.time.format("%H:%M:%S"),
+                    entry.level,
+                    entry.target,
+                    entry.message
+                ))
+            })
+            .collect()
     };
 
-    if content_text_width <= content_available_width {
-        displayed_string.push_str(content_text);
-    } else {
-        let spacer = "   ***   ";
-        let content = format!("{}{}", content_text, spacer);
-        let content_width = content.width();
-        
-        let offset = app.scroll_offset % content_width;
-        let mut current_width = 0;
-        let mut iter = content.chars().cycle();
-        
-        // Skip chars
-        let mut skipped_width = 0;
-        for c in iter.by_ref() {
-            let w = c.width().unwrap_or(0);
-            if skipped_width + w > offset {
-                displayed_string.push(c);
-                current_width += w;
-                break;
-            }
-            skipped_width += w;
-        }
-
-        // Fill remaining
-        for c in iter {
-            if current_width >= content_available_width {
-                break;
-            }
-            let w = c.width().unwrap_or(0);
-            displayed_string.push(c);
-            current_width += w;
-        }
+    let mut list = List::new(items).style(style);
+    if app.config.show_frame {
+        list = list.block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Infotube - Log (l: close)")
+                .style(style),
+        );
     }
-
-    Paragraph::new(displayed_string)
-        .alignment(alignment)
-        .style(style)
+    f.render_widget(list, area);
 }
@@ -1,36 +1,107 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
+use futures::future::BoxFuture;
 use futures_util::StreamExt;
-use serde_json::Value;
 use std::time::Duration;
-use tokio::sync::mpsc;
 use tokio::time;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, Message as WsMessage};
+use tracing::{debug, info, warn};
 use url::Url;
 
+use crate::backoff::Backoff;
 use crate::config::WsConfig;
-use crate::event::Event;
-use crate::json;
+use crate::items::ItemTracker;
+use crate::source::{PayloadSpec, Source, SourceContext};
 
-pub fn start(ws_configs: Vec<WsConfig>, tx: mpsc::UnboundedSender<Event>) {
-    for config in ws_configs {
-        if !config.enabled {
-            continue;
-        }
+/// How long to wait for the server to acknowledge our close frame.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many element ids (`items`) a source remembers to skip repeats.
+const RECENT_ITEMS: usize = 1000;
+
+/// Which side ended a connection.
+enum Disconnect {
+    /// The server closed the connection or the stream ended.
+    Remote,
+    /// We closed it, because of shutdown or because the source was disabled.
+    Local,
+}
+
+/// Listens on a WebSocket, reconnecting with backoff when the connection
+/// is lost.
+pub struct WsSource {
+    config: WsConfig,
+}
+
+impl WsSource {
+    pub fn new(config: WsConfig) -> Self {
+        Self { config }
+    }
 
-        let tx = tx.clone();
-        tokio::spawn(async move {
-            loop {
-                if let Err(e) = connect_and_listen(&config, &tx).await {
-                    eprintln!("WebSocket Error [{}]: {:?}", config.name, e);
+    async fn listen(self, ctx: SourceContext) {
+        // Shared across reconnects so that an element sent again is not shown twice
+        let mut tracker = ItemTracker::recent(RECENT_ITEMS);
+        let mut backoff = Backoff::new(self.config.backoff.clone());
+        loop {
+            if ctx.is_shutting_down() {
+                return;
+            }
+            if !ctx.is_enabled() {
+                tokio::select! {
+                    _ = ctx.wait_until_enabled() => backoff.reset(),
+                    _ = ctx.shutdown() => return,
                 }
-                // Retry delay
-                time::sleep(Duration::from_secs(5)).await;
             }
-        });
+
+            let result = connect_and_listen(&self.config, &ctx, &mut tracker, &mut backoff).await;
+            let e = match result {
+                Ok(Disconnect::Local) => continue,
+                Ok(Disconnect::Remote) => anyhow!("connection closed"),
+                Err(e) => e,
+            };
+            ctx.failure(&e);
+
+            tokio::select! {
+                _ = time::sleep(backoff.next_delay()) => {}
+                _ = ctx.shutdown() => return,
+            }
+        }
+    }
+}
+
+impl Source for WsSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn kind(&self) -> &'static str {
+        "ws"
+    }
+
+    fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn run(self: Box<Self>, ctx: SourceContext) -> BoxFuture<'static, ()> {
+        // Not dropped on shutdown: the connection is closed cleanly instead
+        Box::pin(self.listen(ctx))
     }
 }
 
-async fn connect_and_listen(config: &WsConfig, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+/// Connects and forwards messages until the connection ends, the source is
+/// disabled or shutdown starts. In the latter cases a close frame is sent.
+///
+/// The connection counts as established (the source is reported connected
+/// and the backoff is reset) once the first data frame arrives or it has
+/// stayed open for `backoff.max_sec`. A server that accepts and then closes
+/// right away is therefore retried with growing delays.
+async fn connect_and_listen(
+    config: &WsConfig,
+    ctx: &SourceContext,
+    tracker: &mut ItemTracker,
+    backoff: &mut Backoff,
+) -> Result<Disconnect> {
     let url = Url::parse(&config.url)?;
     
     // Auto-convert https to wss if needed, though usually user should provide wss
@@ -42,35 +113,69 @@ async fn connect_and_listen(config: &WsConfig, tx: &mpsc::UnboundedSender<Event>
         config.url.clone()
     };
 
-    let (ws_stream, _) = connect_async(&url_str).await?;
-    let (_, mut read) = ws_stream.split();
+    let stop = ctx.stopped();
+    tokio::pin!(stop);
+
+    let (mut ws_stream, _) = tokio::select! {
+        result = connect_async(&url_str) => result?,
+        _ = &mut stop => return Ok(Disconnect::Local),
+    };
+    info!(source = %config.name, url = %url_str, "websocket connected");
+
+    let stable = time::sleep(Duration::from_secs(config.backoff.max_sec.max(1)));
+    tokio::pin!(stable);
+    let mut established = false;
+    let mut establish = |established: &mut bool| {
+        if !*established {
+            *established = true;
+            ctx.success();
+            backoff.reset();
+        }
+    };
+
+    loop {
+        let msg = tokio::select! {
+            msg = ws_stream.next() => msg,
+            _ = &mut stable, if !established => {
+                establish(&mut established);
+                continue;
+            }
+            _ = &mut stop => {
+                let frame = CloseFrame {
+                    code: CloseCode::Away,
+                    reason: "client shutting down".into(),
+                };
+                if time::timeout(CLOSE_TIMEOUT, ws_stream.close(Some(frame))).await.is_err() {
+                    warn!(source = %config.name, "timed out closing websocket");
+                }
+                info!(source = %config.name, "websocket closed");
+                return Ok(Disconnect::Local);
+            }
+        };
+        let Some(msg) = msg else {
+            return Ok(Disconnect::Remote);
+        };
 
-    while let Some(msg) = read.next().await {
         match msg? {
-            Message::Text(text) => {
-                if let Some(display_text) = parse_message(&text, &config.json_keys) {
-                    let _ = tx.send(Event::Message(format!("[{}] {}", config.name, display_text)));
+            WsMessage::Text(text) => {
+                establish(&mut established);
+                // A frame that is not valid JSON (e.g. a heartbeat) is skipped, but the connection is kept
+                match PayloadSpec::from(config).messages(text.as_bytes(), tracker) {
+                    Ok(messages) => {
+                        for msg in messages {
+                            ctx.emit(msg);
+                        }
+                    }
+                    Err(e) => debug!(source = %config.name, error = %e, "ignoring frame that is not valid JSON"),
                 }
             }
-            Message::Binary(_) => {} 
-            Message::Ping(_) | Message::Pong(_) => {}
-            Message::Close(_) => return Ok(()),
-            Message::Frame(_) => {}
+            WsMessage::Binary(_) => establish(&mut established),
+            WsMessage::Ping(_) | WsMessage::Pong(_) => {}
+            WsMessage::Close(frame) => match frame {
+                Some(frame) => bail!("closed by server: {} {}", frame.code, frame.reason),
+                None => bail!("closed by server"),
+            },
+            WsMessage::Frame(_) => {}
         }
     }
-
-    Ok(())
-}
-
-fn parse_message(text: &str, keys: &Option<Vec<String>>) -> Option<String> {
-    let keys = match keys {
-        Some(k) => k,
-        None => return Some(text.to_string()),
-    };
-
-    let Ok(json) = serde_json::from_str::<Value>(text) else {
-        return None;
-    };
-
-    json::extract_message(&json, keys)
 }
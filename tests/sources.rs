@@ -0,0 +1,471 @@
+//! Integration tests of the API and WebSocket sources against in-process
+//! mock servers.
+
+use futures_util::{SinkExt, StreamExt};
+use infotube::config::{ApiConfig, WsConfig};
+use infotube::{
+    ApiSource, HealthRegistry, Message, MessageQueue, OverflowPolicy, Shutdown, SourceHealth, SourceRegistry,
+    SourceState, WsSource,
+};
+use serde_json::json;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{Instant, sleep, timeout};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, Message as WsMessage};
+
+/// Longest wait for something that should happen.
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Retries after at most one second.
+const FAST_RETRY: &str = "backoff = { initial_sec = 1, max_sec = 1 }";
+
+/// Sources under test and the queue they send to.
+struct Harness {
+    queue: MessageQueue,
+    health: HealthRegistry,
+    shutdown: Shutdown,
+}
+
+impl Harness {
+    fn start(registry: SourceRegistry) -> Self {
+        let health = HealthRegistry::new();
+        let shutdown = Shutdown::new();
+        let queue = MessageQueue::new(100, OverflowPolicy::DropNewest, health.clone());
+        registry.start(queue.sender(), health.clone(), shutdown.clone());
+        Self {
+            queue,
+            health,
+            shutdown,
+        }
+    }
+
+    fn api(config: &str) -> Self {
+        let mut registry = SourceRegistry::new();
+        registry.add(ApiSource::new(toml::from_str::<ApiConfig>(config).unwrap(), reqwest::Client::new()));
+        Self::start(registry)
+    }
+
+    fn ws(config: &str) -> Self {
+        let mut registry = SourceRegistry::new();
+        registry.add(WsSource::new(toml::from_str::<WsConfig>(config).unwrap()));
+        Self::start(registry)
+    }
+
+    async fn recv(&self) -> Message {
+        timeout(WAIT, self.queue.recv()).await.expect("no message received")
+    }
+
+    /// Asserts that no message arrives for a while.
+    async fn assert_silent(&self) {
+        if let Ok(msg) = timeout(Duration::from_millis(300), self.queue.recv()).await {
+            panic!("unexpected message: {:?}", msg.text);
+        }
+    }
+
+    fn health(&self, name: &str) -> SourceHealth {
+        self.health
+            .snapshot()
+            .into_iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, health)| health)
+            .unwrap()
+    }
+
+    /// Waits until the source has failed `count` times and returns the last error.
+    async fn failure(&self, name: &str, count: u64) -> String {
+        timeout(WAIT, async {
+            loop {
+                let health = self.health(name);
+                if health.error_count >= count {
+                    return health.last_error.unwrap();
+                }
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("source did not fail")
+    }
+}
+
+/// A canned HTTP response.
+#[derive(Clone)]
+struct Response {
+    status: u16,
+    body: String,
+    delay: Duration,
+    /// Sent as `ETag`; a request with a matching `If-None-Match` gets a 304.
+    etag: Option<String>,
+}
+
+impl Response {
+    fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+            delay: Duration::ZERO,
+            etag: None,
+        }
+    }
+
+    fn etag(mut self, etag: &str) -> Self {
+        self.etag = Some(etag.to_string());
+        self
+    }
+
+    fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Serves `responses` in order, repeating the last one. Returns the base URL
+/// and the number of requests received.
+async fn http_server(responses: Vec<Response>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let count = requests.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let n = count.fetch_add(1, Ordering::SeqCst);
+            let response = responses[n.min(responses.len() - 1)].clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut socket).await.to_ascii_lowercase();
+                sleep(response.delay).await;
+                let (status, body, etag) = match &response.etag {
+                    Some(etag) if request.contains(&format!("if-none-match: {}", etag)) => {
+                        (304, "", format!("ETag: {}\r\n", etag))
+                    }
+                    Some(etag) => (response.status, response.body.as_str(), format!("ETag: {}\r\n", etag)),
+                    None => (response.status, response.body.as_str(), String::new()),
+                };
+                let head = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                    status,
+                    body.len(),
+                    etag
+                );
+                let _ = socket.write_all(head.as_bytes()).await;
+                let _ = socket.write_all(body.as_bytes()).await;
+            });
+        }
+    });
+
+    (format!("http://{}", addr), requests)
+}
+
+/// Reads the head of a request without a body.
+async fn read_request(socket: &mut TcpStream) -> String {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        match socket.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+        }
+    }
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+/// Accepts WebSocket connections and runs `handler` for each, with the
+/// number of the connection (starting at 0).
+async fn ws_server<F, Fut>(handler: F) -> SocketAddr
+where
+    F: Fn(usize, WebSocketStream<TcpStream>) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        for n in 0.. {
+            let (socket, _) = listener.accept().await.unwrap();
+            let ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            tokio::spawn(handler(n, ws));
+        }
+    });
+
+    addr
+}
+
+/// Keeps the connection open until the client goes away.
+async fn drain(mut ws: WebSocketStream<TcpStream>) {
+    while let Some(Ok(_)) = ws.next().await {}
+}
+
+#[tokio::test]
+async fn api_extracts_json_fields() {
+    let body = json!({"quake": {"place": "Tokyo", "scale": 3}}).to_string();
+    let (url, _) = http_server(vec![Response::new(200, body)]).await;
+    let harness = Harness::api(&format!(
+        r#"
+        name = "quake"
+        url = "{url}/latest"
+        json_keys = ["quake/place", "$.quake.scale"]
+        "#
+    ));
+
+    let msg = harness.recv().await;
+    assert_eq!(msg.source, "quake");
+    assert_eq!(msg.text, "[quake] Tokyo 3");
+    assert_eq!(msg.json.unwrap()["quake"]["scale"], 3);
+    assert_eq!(harness.health("quake").error_count, 0);
+}
+
+#[tokio::test]
+async fn api_plain_text_is_shown_as_is() {
+    let (url, _) = http_server(vec![Response::new(200, "all good")]).await;
+    let harness = Harness::api(&format!("name = \"status\"\nurl = \"{url}\""));
+
+    assert_eq!(harness.recv().await.text, "[status] all good");
+}
+
+#[tokio::test]
+async fn api_reports_non_2xx_responses() {
+    let (url, _) = http_server(vec![Response::new(503, "down")]).await;
+    let harness = Harness::api(&format!("name = \"svc\"\nurl = \"{url}\""));
+
+    let error = harness.failure("svc", 1).await;
+    assert_eq!(error, "HTTP 503 Service Unavailable");
+    harness.assert_silent().await;
+}
+
+#[tokio::test]
+async fn api_reports_malformed_json() {
+    let (url, _) = http_server(vec![Response::new(200, "{not json")]).await;
+    let harness = Harness::api(&format!(
+        "name = \"svc\"\nurl = \"{url}\"\njson_keys = [\"value\"]"
+    ));
+
+    let error = harness.failure("svc", 1).await;
+    assert!(error.contains("key must be a string"), "{}", error);
+    harness.assert_silent().await;
+}
+
+#[tokio::test]
+async fn api_does_not_revalidate_a_payload_that_failed() {
+    let (url, requests) = http_server(vec![Response::new(200, "{not json").etag("\"v1\"")]).await;
+    let harness = Harness::api(&format!(
+        r#"
+        name = "svc"
+        url = "{url}"
+        interval_sec = 1
+        json_keys = ["value"]
+        {FAST_RETRY}
+        "#
+    ));
+
+    // A 304 for the broken payload would count as a success
+    harness.failure("svc", 2).await;
+    assert!(requests.load(Ordering::SeqCst) >= 2);
+    assert_eq!(harness.health("svc").last_success, None);
+}
+
+#[tokio::test]
+async fn api_recovers_after_a_failure() {
+    let (url, requests) = http_server(vec![
+        Response::new(500, ""),
+        Response::new(200, r#"{"value": "back"}"#),
+    ])
+    .await;
+    let harness = Harness::api(&format!(
+        r#"
+        name = "svc"
+        url = "{url}"
+        interval_sec = 1
+        json_keys = ["value"]
+        {FAST_RETRY}
+        "#
+    ));
+
+    assert_eq!(harness.failure("svc", 1).await, "HTTP 500 Internal Server Error");
+    assert_eq!(harness.recv().await.text, "[svc] back");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    let health = harness.health("svc");
+    assert_eq!(health.consecutive_failures, 0);
+    assert!(health.last_success.is_some());
+}
+
+#[tokio::test]
+async fn api_times_out_slow_responses() {
+    let (url, _) =
+        http_server(vec![Response::new(200, "late").delayed(Duration::from_secs(3))]).await;
+    let harness = Harness::api(&format!(
+        "name = \"slow\"\nurl = \"{url}\"\ntimeout_sec = 1"
+    ));
+
+    let error = harness.failure("slow", 1).await;
+    assert!(error.contains("timed out"), "{}", error);
+    harness.assert_silent().await;
+}
+
+#[tokio::test]
+async fn ws_extracts_json_fields_and_ignores_other_frames() {
+    let addr = ws_server(|_, mut ws| async move {
+        ws.send(WsMessage::Binary(vec![1, 2, 3].into())).await.unwrap();
+        ws.send(WsMessage::text("{broken")).await.unwrap();
+        ws.send(WsMessage::text(r#"{"user": "alice", "text": "hi"}"#))
+            .await
+            .unwrap();
+        drain(ws).await;
+    })
+    .await;
+    let harness = Harness::ws(&format!(
+        "name = \"chat\"\nurl = \"ws://{addr}\"\ntemplate = \"{{user}}: {{text}}\""
+    ));
+
+    let msg = harness.recv().await;
+    assert_eq!(msg.text, "[chat] alice: hi");
+    assert_eq!(msg.json.unwrap()["user"], "alice");
+    harness.assert_silent().await;
+    // A frame that is not JSON does not make the connection look broken
+    let health = harness.health("chat");
+    assert_eq!(health.state, SourceState::Connected);
+    assert_eq!(health.error_count, 0);
+}
+
+#[tokio::test]
+async fn ws_does_not_show_an_item_sent_again() {
+    let addr = ws_server(|_, mut ws| async move {
+        for id in ["a", "b", "a", "c"] {
+            ws.send(WsMessage::text(json!({ "quake": { "id": id } }).to_string()))
+                .await
+                .unwrap();
+        }
+        drain(ws).await;
+    })
+    .await;
+    let harness = Harness::ws(&format!(
+        "name = \"quake\"\nurl = \"ws://{addr}\"\nitems = \"$.quake\"\nid_field = \"id\"\ntemplate = \"{{id}}\""
+    ));
+
+    assert_eq!(harness.recv().await.text, "[quake] a");
+    assert_eq!(harness.recv().await.text, "[quake] b");
+    assert_eq!(harness.recv().await.text, "[quake] c");
+    harness.assert_silent().await;
+}
+
+#[tokio::test]
+async fn ws_reconnects_after_a_close_frame() {
+    let addr = ws_server(|n, mut ws| async move {
+        if n == 0 {
+            let frame = CloseFrame {
+                code: CloseCode::Again,
+                reason: "restarting".into(),
+            };
+            let _ = ws.close(Some(frame)).await;
+        } else {
+            ws.send(WsMessage::text("reconnected")).await.unwrap();
+            drain(ws).await;
+        }
+    })
+    .await;
+    let harness = Harness::ws(&format!("name = \"feed\"\nurl = \"ws://{addr}\"\n{FAST_RETRY}"));
+
+    let error = harness.failure("feed", 1).await;
+    assert_eq!(error, "closed by server: 1013 restarting");
+    assert_eq!(harness.recv().await.text, "[feed] reconnected");
+    assert_eq!(harness.health("feed").consecutive_failures, 0);
+}
+
+#[tokio::test]
+async fn ws_reconnects_after_a_dropped_connection() {
+    let addr = ws_server(|n, mut ws| async move {
+        ws.send(WsMessage::text(format!("connection {}", n)))
+            .await
+            .unwrap();
+        if n == 0 {
+            // Drop the TCP connection without a close frame
+            drop(ws);
+        } else {
+            drain(ws).await;
+        }
+    })
+    .await;
+    let harness = Harness::ws(&format!("name = \"feed\"\nurl = \"ws://{addr}\"\n{FAST_RETRY}"));
+
+    assert_eq!(harness.recv().await.text, "[feed] connection 0");
+    assert_eq!(harness.recv().await.text, "[feed] connection 1");
+    assert_eq!(harness.health("feed").error_count, 1);
+}
+
+#[tokio::test]
+async fn ws_backs_off_when_the_server_closes_right_away() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let addr = ws_server(move |_, mut ws| {
+        let tx = tx.clone();
+        async move {
+            let _ = tx.send(Instant::now());
+            let _ = ws.close(None).await;
+        }
+    })
+    .await;
+    // Delays of 0.5-1s, then 2-4s
+    let harness = Harness::ws(&format!(
+        "name = \"feed\"\nurl = \"ws://{addr}\"\nbackoff = {{ initial_sec = 1, max_sec = 8, multiplier = 4.0 }}"
+    ));
+
+    let mut accepted = Vec::new();
+    for _ in 0..3 {
+        accepted.push(timeout(WAIT, rx.recv()).await.unwrap().unwrap());
+    }
+    let first = accepted[1] - accepted[0];
+    let second = accepted[2] - accepted[1];
+    assert!(second >= Duration::from_millis(1900), "{:?} then {:?}", first, second);
+    assert!(second > first, "{:?} then {:?}", first, second);
+
+    // An accepted handshake alone does not count as connected
+    let health = harness.health("feed");
+    assert!(health.last_success.is_none());
+    assert_ne!(health.state, SourceState::Connected);
+}
+
+#[tokio::test]
+async fn ws_sends_a_close_frame_on_shutdown() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let addr = ws_server(move |_, mut ws| {
+        let tx = tx.clone();
+        async move {
+            ws.send(WsMessage::text("hello")).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if let WsMessage::Close(frame) = msg {
+                    let _ = tx.send(frame);
+                }
+            }
+        }
+    })
+    .await;
+    let harness = Harness::ws(&format!("name = \"feed\"\nurl = \"ws://{addr}\""));
+
+    assert_eq!(harness.recv().await.text, "[feed] hello");
+    assert!(harness.shutdown.shutdown(WAIT).await);
+
+    let frame = timeout(WAIT, rx.recv()).await.unwrap().unwrap().unwrap();
+    assert_eq!(frame.code, CloseCode::Away);
+    assert_eq!(frame.reason, "client shutting down");
+}
+
+#[tokio::test]
+async fn ws_reports_refused_connections() {
+    // Bind and release a port so that nothing listens on it
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let harness = Harness::ws(&format!("name = \"feed\"\nurl = \"ws://{addr}\""));
+
+    let error = harness.failure("feed", 1).await;
+    assert!(error.contains("Connection refused"), "{}", error);
+}
@@ -0,0 +1,319 @@
+//! Snapshot tests of the rendered UI, driven by events without a terminal.
+
+use chrono::{DateTime, Local, TimeZone};
+use crossterm::event::{KeyCode, KeyEvent};
+use infotube::recording::{self, Recording};
+use infotube::{App, Clock, Config, Event, HealthRegistry, LogBuffer, Message, Priority, ui};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use ratatui::style::Color;
+use std::path::Path;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+fn app(config: Config, text: &str, width: u16) -> App {
+    let mut app = App::new(config, HealthRegistry::new(), LogBuffer::default()).with_text(text);
+    app.handle_event(Event::Resize(width));
+    app
+}
+
+fn no_frame() -> Config {
+    Config {
+        show_frame: false,
+        ..Config::default()
+    }
+}
+
+fn ticks(app: &mut App, count: usize) {
+    for _ in 0..count {
+        app.handle_event(Event::Tick);
+    }
+}
+
+/// A time on Monday, 6 January 2025.
+fn at(hour: u32, min: u32, sec: u32) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 1, 6, hour, min, sec).unwrap()
+}
+
+fn key(app: &mut App, c: char) {
+    app.handle_event(Event::Key(KeyEvent::from(KeyCode::Char(c))));
+}
+
+/// A recording in tests/fixtures.
+fn fixture(name: &str) -> Recording {
+    recording::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)).unwrap()
+}
+
+/// Sends the recorded events up to `until_ms` to `app`, setting `clock` to
+/// the time each was recorded at, like `--replay` does.
+fn replay(app: &mut App, clock: &Clock, recording: &Recording, from_ms: u64, until_ms: u64) {
+    for recorded in recording.events.iter().filter(|e| (from_ms..=until_ms).contains(&e.t_ms)) {
+        clock.set(recording.time_of(recorded));
+        app.handle_event(recorded.event.clone());
+    }
+}
+
+fn draw(app: &mut App, width: u16, height: u16) -> Buffer {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| ui::draw(f, app)).unwrap();
+    terminal.backend().buffer().clone()
+}
+
+/// The text of each row; wide characters count once.
+fn lines(buf: &Buffer) -> Vec<String> {
+    (0..buf.area.height)
+        .map(|y| {
+            let mut line = String::new();
+            let mut x = 0;
+            while x < buf.area.width {
+                let symbol = buf[(x, y)].symbol();
+                line.push_str(symbol);
+                x += symbol.width().max(1) as u16;
+            }
+            line
+        })
+        .collect()
+}
+
+#[test]
+fn frame_centers_short_text() {
+    let mut app = app(Config::default(), "Hello", 20);
+    let buf = draw(&mut app, 20, 3);
+    assert_eq!(
+        lines(&buf),
+        [
+            "┌Infotube──────────┐",
+            "│       Hello      │",
+            "└──────────────────┘",
+        ]
+    );
+}
+
+#[test]
+fn no_frame_uses_the_first_row_left_aligned() {
+    let mut app = app(no_frame(), "Hello", 12);
+    let buf = draw(&mut app, 12, 2);
+    assert_eq!(lines(&buf), ["Hello       ", "            "]);
+}
+
+#[test]
+fn short_text_does_not_scroll() {
+    let mut app = app(no_frame(), "Hello", 12);
+    ticks(&mut app, 5);
+    let buf = draw(&mut app, 12, 1);
+    assert_eq!(lines(&buf), ["Hello       "]);
+}
+
+#[test]
+fn long_text_scrolls_and_wraps_around() {
+    let mut app = app(no_frame(), "abcdefghij", 6);
+    assert_eq!(lines(&draw(&mut app, 6, 1)), ["abcdef"]);
+
+    ticks(&mut app, 3);
+    assert_eq!(lines(&draw(&mut app, 6, 1)), ["defghi"]);
+
+    // "abcdefghij   ***   " is 19 columns wide
+    ticks(&mut app, 9);
+    assert_eq!(lines(&draw(&mut app, 6, 1)), [" ***  "]);
+
+    ticks(&mut app, 9);
+    assert_eq!(lines(&draw(&mut app, 6, 1)), ["cdefgh"]);
+}
+
+#[test]
+fn long_text_scrolls_inside_the_frame() {
+    let mut app = app(Config::default(), "abcdefghij", 8);
+    ticks(&mut app, 2);
+    let buf = draw(&mut app, 8, 3);
+    assert_eq!(lines(&buf), ["┌Infotu┐", "│cdefgh│", "└──────┘"]);
+}
+
+#[test]
+fn wide_characters_scroll_by_column() {
+    let mut app = app(no_frame(), "日本語のテキストです", 8);
+    assert_eq!(lines(&draw(&mut app, 8, 1)), ["日本語の"]);
+
+    ticks(&mut app, 2);
+    assert_eq!(lines(&draw(&mut app, 8, 1)), ["本語のテ"]);
+}
+
+#[test]
+fn interrupt_shows_countdown_then_returns_to_the_text() {
+    let config = Config {
+        interrupt_duration_sec: 3,
+        ..Config::default()
+    };
+    let mut app = app(config, "Hello", 20);
+    app.handle_event(Event::Message(Message::new("test", "Alert!", Priority::High)));
+
+    let buf = draw(&mut app, 20, 3);
+    assert_eq!(
+        lines(&buf),
+        [
+            "┌Infotube - ALERT──┐",
+            "│(3s)  Alert!      │",
+            "└──────────────────┘",
+        ]
+    );
+    assert_eq!(buf[(1, 1)].fg, Color::Red);
+
+    // Ticks are 100ms apart
+    ticks(&mut app, 10);
+    assert_eq!(lines(&draw(&mut app, 20, 3))[1], "│(2s)  Alert!      │");
+
+    ticks(&mut app, 20);
+    let buf = draw(&mut app, 20, 3);
+    assert_eq!(lines(&buf)[1], "│       Hello      │");
+    assert_eq!(buf[(8, 1)].fg, Color::White);
+}
+
+#[test]
+fn enter_dismisses_the_interrupt() {
+    let mut app = app(no_frame(), "Hello", 20);
+    app.handle_event(Event::Message(Message::new("test", "Alert!", Priority::High)));
+    app.handle_event(Event::Key(KeyEvent::from(KeyCode::Enter)));
+    assert_eq!(lines(&draw(&mut app, 20, 1)), ["Hello               "]);
+}
+
+#[test]
+fn pause_stops_scrolling() {
+    let mut app = app(Config::default(), "abcdefghij", 8);
+    ticks(&mut app, 2);
+    key(&mut app, ' ');
+    ticks(&mut app, 5);
+
+    let buf = draw(&mut app, 30, 3);
+    assert_eq!(lines(&buf)[0], "┌Infotube (Paused)───────────┐");
+
+    let buf = draw(&mut app, 8, 3);
+    assert_eq!(lines(&buf)[1], "│cdefgh│");
+
+    key(&mut app, ' ');
+    ticks(&mut app, 1);
+    assert_eq!(lines(&draw(&mut app, 8, 3))[1], "│defghi│");
+}
+
+#[test]
+fn dim_grays_out_the_text() {
+    let mut app = app(no_frame(), "Hello", 10);
+    assert_eq!(draw(&mut app, 10, 1)[(0, 0)].fg, Color::White);
+
+    key(&mut app, 'b');
+    let buf = draw(&mut app, 10, 1);
+    assert_eq!(lines(&buf), ["Hello     "]);
+    assert_eq!(buf[(0, 0)].fg, Color::DarkGray);
+}
+
+#[test]
+fn dnd_schedule_follows_the_clock() {
+    let config = Config {
+        dnd: toml::from_str(r#"schedule = [{ start = "22:00", end = "07:00" }]"#).unwrap(),
+        ..Config::default()
+    };
+    let clock = Clock::manual(at(21, 59, 55));
+    let mut app = app(config, "Hello", 24).with_clock(clock.clone());
+
+    ticks(&mut app, 1);
+    assert_eq!(lines(&draw(&mut app, 24, 3))[0], "┌Infotube──────────────┐");
+
+    clock.advance(Duration::from_secs(5));
+    ticks(&mut app, 1);
+    assert_eq!(lines(&draw(&mut app, 24, 3))[0], "┌Infotube (DND)────────┐");
+}
+
+#[test]
+fn dnd_summary_counts_every_message_but_shows_only_the_latest() {
+    let mut app = app(Config::default(), "Hello", 24);
+    key(&mut app, 'd');
+    for n in 1..=50 {
+        let source = if n % 10 == 0 { "Chat" } else { "Feed" };
+        app.handle_event(Event::Message(Message::new(source, format!("m{}", n), Priority::Low)));
+    }
+    assert!(app.ticker.interrupt.is_none());
+
+    key(&mut app, 'd');
+    let summary = &app.ticker.interrupt.as_ref().unwrap().text;
+    assert_eq!(summary, "DND ended: 50 message(s) received (Feed 45, Chat 5)    latest 3: m48    m49    m50");
+}
+
+#[test]
+fn duplicates_are_suppressed_until_quiet_for_the_ttl() {
+    let mut config = no_frame();
+    config.listener.rate_limit = Some(toml::from_str("dedup_ttl_sec = 60").unwrap());
+    let clock = Clock::manual(at(12, 0, 0));
+    let mut app = app(config, "Hello", 20).with_clock(clock.clone());
+    let ping = || Event::Message(Message::new("Listener", "Ping", Priority::High));
+
+    app.handle_event(ping());
+    assert_eq!(lines(&draw(&mut app, 20, 1)), ["(9s)  Ping          "]);
+    app.handle_event(Event::Key(KeyEvent::from(KeyCode::Enter)));
+
+    clock.advance(Duration::from_secs(59));
+    app.handle_event(ping());
+    assert_eq!(lines(&draw(&mut app, 20, 1)), ["Hello               "]);
+
+    // The suppressed duplicate restarted the TTL
+    clock.advance(Duration::from_secs(59));
+    app.handle_event(ping());
+    assert_eq!(lines(&draw(&mut app, 20, 1)), ["Hello               "]);
+
+    clock.advance(Duration::from_secs(60));
+    app.handle_event(ping());
+    assert_eq!(lines(&draw(&mut app, 20, 1)), ["(9s)  Ping          "]);
+}
+
+#[test]
+fn history_shows_the_clock_time() {
+    let clock = Clock::manual(at(8, 30, 0));
+    let mut app = app(no_frame(), "Hello", 40).with_clock(clock.clone());
+    app.handle_event(Event::Message(Message::new("test", "first", Priority::High)));
+    clock.advance(Duration::from_secs(15 * 60));
+    app.handle_event(Event::Message(Message::new("test", "second", Priority::High)));
+
+    key(&mut app, 'h');
+    app.handle_event(Event::Key(KeyEvent::from(KeyCode::Enter)));
+    assert_eq!(
+        lines(&draw(&mut app, 40, 1)),
+        ["08:45 second    ***    08:30 first      "]
+    );
+}
+
+#[test]
+fn recorded_session_replays_to_the_same_screens() {
+    let recording = fixture("interrupt.jsonl");
+    let clock = Clock::manual(recording.start);
+    let mut app = App::new(no_frame(), HealthRegistry::new(), LogBuffer::default())
+        .with_text("Hello")
+        .with_clock(clock.clone());
+    replay(&mut app, &clock, &recording, 0, 400);
+    assert_eq!(lines(&draw(&mut app, 24, 1)), ["(9s)  [api] Build passed"]);
+
+    replay(&mut app, &clock, &recording, 401, u64::MAX);
+    assert_eq!(lines(&draw(&mut app, 24, 1)), ["Hello                   "]);
+}
+
+#[test]
+fn replay_reproduces_the_recorded_time_of_day() {
+    // Recorded from one second before the start of a minute
+    let recording = fixture("dnd.jsonl");
+    let dnd_start = recording.start + Duration::from_secs(1);
+    let window = format!(
+        r#"schedule = [{{ start = "{}", end = "{}" }}]"#,
+        dnd_start.format("%H:%M"),
+        (dnd_start + Duration::from_secs(3600)).format("%H:%M")
+    );
+    let config = Config {
+        dnd: toml::from_str(&window).unwrap(),
+        ..Config::default()
+    };
+    let clock = Clock::manual(recording.start);
+    let mut app = App::new(config, HealthRegistry::new(), LogBuffer::default())
+        .with_text("Hello")
+        .with_clock(clock.clone());
+
+    replay(&mut app, &clock, &recording, 0, 1000);
+    assert_eq!(lines(&draw(&mut app, 24, 3))[0], "┌Infotube──────────────┐");
+    replay(&mut app, &clock, &recording, 1001, u64::MAX);
+    assert_eq!(lines(&draw(&mut app, 24, 3))[0], "┌Infotube (DND)────────┐");
+}